@@ -1,4 +1,6 @@
 //! https://mutoys.com/so2/info/api
 
+pub mod fragment;
 pub mod model;
 pub mod schema;
+pub mod stream;
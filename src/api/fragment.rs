@@ -0,0 +1,208 @@
+//! 注文レポートの応答形式: 配列の要素だけをカンマ区切りで並べたもの
+//!
+//! e.g. `[1,"a",...],[2,"b",...],` (`[` `]` で囲まれておらず, 末尾のカンマや空白は不定)
+//!
+//! 以前のキャッシュは全体を `[` `]` で囲んで保存していたので, その形式も読める
+
+use serde::de::{self, DeserializeSeed, IgnoredAny, SeqAccess, Visitor};
+use serde::{forward_to_deserialize_any, ser};
+use serde_json::Error;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// 全体を1つの配列として読む
+pub fn from_slice<'de, T: de::Deserialize<'de>>(bytes: &'de [u8]) -> Result<T, Error> {
+    T::deserialize(Fragments::new(bytes))
+}
+
+/// 配列を同じ形式で書き出す (末尾のカンマは付けない)
+pub fn to_vec<T: ser::Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+    let json = serde_json::to_vec(value)?;
+    match json
+        .strip_prefix(b"[")
+        .and_then(|json| json.strip_suffix(b"]"))
+    {
+        Some(elements) => Ok(elements.to_vec()),
+        None => Err(ser::Error::custom("expected an array")),
+    }
+}
+
+/// 要素を1つずつ読む [Deserializer](de::Deserializer)
+///
+/// 要素の間や前後の空白とカンマは (連続していても) 読み飛ばす
+#[derive(Debug)]
+pub struct Fragments<'de> {
+    bytes: &'de [u8],
+    pos: usize,
+}
+
+impl<'de> Fragments<'de> {
+    pub fn new(bytes: &'de [u8]) -> Self {
+        let bytes = bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes);
+        let bytes = enclosed(bytes).unwrap_or(bytes);
+        Self { bytes, pos: 0 }
+    }
+
+    fn skip_separators(&mut self) {
+        while let Some(b) = self.bytes.get(self.pos) {
+            if !b.is_ascii_whitespace() && *b != b',' {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+
+    /// 次の要素のバイト列, 無ければ `None`
+    fn next_value(&mut self) -> Result<Option<&'de [u8]>, Error> {
+        self.skip_separators();
+        let rest = &self.bytes[self.pos..];
+        if rest.is_empty() {
+            return Ok(None);
+        }
+        let mut stream = serde_json::Deserializer::from_slice(rest).into_iter::<IgnoredAny>();
+        match stream.next() {
+            Some(Ok(_)) => {}
+            Some(Err(e)) => return Err(e),
+            None => return Ok(None),
+        }
+        let len = stream.byte_offset();
+        self.pos += len;
+        Ok(Some(&rest[..len]))
+    }
+}
+
+/// 全体が `[` `]` で囲まれた配列 (`[[..],[..]]` や `[]`) であれば, その中身
+///
+/// 要素は配列なので, 中身が `[` で始まらない `[1,..],[2,..]` は囲まれていない
+fn enclosed(bytes: &[u8]) -> Option<&[u8]> {
+    let bytes = bytes.trim_ascii();
+    let inner = bytes.strip_prefix(b"[")?.strip_suffix(b"]")?;
+    let first = inner.trim_ascii_start().first();
+    if first.is_some_and(|b| *b != b'[') {
+        return None;
+    }
+    // `[..],[..]` も両端は `[` `]` なので, 全体で1つの値であることを確かめる
+    let mut stream = serde_json::Deserializer::from_slice(bytes).into_iter::<IgnoredAny>();
+    match stream.next() {
+        Some(Ok(_)) if stream.byte_offset() == bytes.len() => Some(inner),
+        _ => None,
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Fragments<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Error> {
+        let value = visitor.visit_seq(&mut self)?;
+        match self.next_value()? {
+            Some(_) => Err(de::Error::custom("trailing fragments")),
+            None => Ok(value),
+        }
+    }
+
+    // `struct Response(Vec<_>)` の中身を配列として読むため
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de> SeqAccess<'de> for Fragments<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        let Some(value) = self.next_value()? else {
+            return Ok(None);
+        };
+        let mut deserializer = serde_json::Deserializer::from_slice(value);
+        seed.deserialize(&mut deserializer).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::model::request_report::Response;
+
+    const ROW_1: &str = r#"[1,"a",2,"b",3,4,500,1500000000]"#;
+    const ROW_2: &str = r#"[6,"c",7,"d",8,9,1000,1500003600]"#;
+
+    fn timestamps(bytes: &[u8]) -> Vec<i64> {
+        let response: Response = from_slice(bytes).unwrap();
+        response.0.iter().map(|row| row.timestamp).collect()
+    }
+
+    #[test]
+    fn separators() {
+        let expected = [1500000000, 1500003600];
+        assert_eq!(timestamps(format!("{ROW_1},{ROW_2}").as_bytes()), expected);
+        assert_eq!(
+            timestamps(format!("{ROW_1},{ROW_2},\n").as_bytes()),
+            expected
+        );
+        assert_eq!(
+            timestamps(format!(" \n{ROW_1} ,\r\n\t{ROW_2} \n").as_bytes()),
+            expected
+        );
+        assert_eq!(
+            timestamps(format!("{ROW_1},,{ROW_2},,").as_bytes()),
+            expected
+        );
+        assert_eq!(timestamps(b""), Vec::<i64>::new());
+        assert_eq!(timestamps(b",\n"), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn bom() {
+        let bytes = [UTF8_BOM, format!("{ROW_1},\n").as_bytes()].concat();
+        assert_eq!(timestamps(&bytes), [1500000000]);
+    }
+
+    #[test]
+    fn enclosed_in_brackets() {
+        let expected = [1500000000, 1500003600];
+        assert_eq!(
+            timestamps(format!("[{ROW_1},{ROW_2}]").as_bytes()),
+            expected
+        );
+        assert_eq!(
+            timestamps(format!(" [ {ROW_1},\n{ROW_2} ]\n").as_bytes()),
+            expected
+        );
+        assert_eq!(timestamps(b"[]"), Vec::<i64>::new());
+        // 1行だけの場合は囲まれていない
+        assert_eq!(timestamps(ROW_1.as_bytes()), [1500000000]);
+        assert_eq!(timestamps(format!("{ROW_1},").as_bytes()), [1500000000]);
+    }
+
+    #[test]
+    fn extra_column() {
+        let bytes = br#"[1,"a",2,"b",3,4,500,1500000000,0],"#;
+        let e = from_slice::<Response>(bytes).unwrap_err();
+        assert!(e.to_string().contains("extra column"), "{e}");
+    }
+
+    #[test]
+    fn trailing_garbage() {
+        assert!(from_slice::<Response>(format!("{ROW_1},x").as_bytes()).is_err());
+    }
+
+    #[test]
+    fn to_vec_round_trip() {
+        let bytes = format!("{ROW_1},{ROW_2}");
+        let response: Response = from_slice(bytes.as_bytes()).unwrap();
+        assert_eq!(to_vec(&response).unwrap(), bytes.as_bytes());
+    }
+}
@@ -9,6 +9,7 @@ pub mod request_report;
 pub mod sale;
 pub mod shop;
 pub mod shop_summary;
+pub mod trade;
 
 mod serde_bool_int {
     use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -9,7 +9,7 @@ use super::item;
 pub type Response = HashMap<Id, Area>;
 
 /// 街定義
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Area {
     /// 街ID
     pub area_id: Id,
@@ -18,7 +18,7 @@ pub struct Area {
     /// 街アピールコメント
     pub desc: Vec<String>,
     /// アピール商品ID
-    #[serde(deserialize_with = "item::deserialize_optional_id")]
+    #[serde(with = "item::serde_id_opt")]
     pub icon: Option<item::Id>,
     /// マップX位置
     pub pos_x: u32,
@@ -33,7 +33,7 @@ pub struct Area {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Id(pub NonZeroU8);
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Name(pub String);
 
 pub mod serde_id_opt {
@@ -1,19 +1,19 @@
 use std::fmt::{Display, Formatter, Result};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use super::area;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Response(pub Vec<AreaSummary>);
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct AreaSummary {
     pub area_id: area::Id,
     pub point: Fun,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Fun(pub i32);
 
 impl Display for AreaSummary {
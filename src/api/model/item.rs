@@ -3,16 +3,16 @@ use std::{collections::HashMap, num::NonZeroU32};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Response {
     #[serde(flatten)]
     value: HashMap<String, Item>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Official(pub Response);
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Recipe(pub Response);
 
 /// ## Note
@@ -27,7 +27,7 @@ pub struct Recipe(pub Response);
 ///   - [Item::name] : "レシピ#元の名前"
 ///   - [Item::scale] : 元のアイテムと同一 (全て"個")
 ///   - [Item::sort] : 元のアイテムと異なる
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Item {
     pub category: Category,
     pub class: Class,
@@ -48,10 +48,10 @@ impl Item {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Category(pub String);
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Class(pub String);
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -67,13 +67,13 @@ impl Id {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub struct StackSize(pub u32);
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Name(pub String);
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Scale(pub String);
 
 impl std::fmt::Display for Item {
@@ -110,10 +110,33 @@ impl Response {
     }
 }
 
+#[deprecated(note = "use `serde_id_opt::deserialize`")]
 pub fn deserialize_optional_id<'de, D>(deserializer: D) -> Result<Option<Id>, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
-    let v = Option::<u32>::deserialize(deserializer)?;
-    Ok(v.and_then(NonZeroU32::new).map(Id))
+    serde_id_opt::deserialize(deserializer)
+}
+
+/// 無い場合は `0` ([Id] は `0` にならない, `null` も無いものとして読む)
+pub mod serde_id_opt {
+    use std::num::NonZeroU32;
+
+    use super::Id;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S>(id: &Option<Id>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        id.as_ref().map_or(0, |id| id.0.get()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Id>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let v = Option::<u32>::deserialize(deserializer)?;
+        Ok(v.and_then(NonZeroU32::new).map(Id))
+    }
 }
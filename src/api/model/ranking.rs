@@ -1,49 +1,50 @@
 use std::collections::HashMap;
 
 use chrono::NaiveDate;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use super::{area, shop};
 
 pub static EPOCH: NaiveDate = NaiveDate::from_ymd_opt(2017, 5, 7).unwrap();
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AllMonthly(pub HashMap<Category, Vec<Info>>);
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SectionMonthly(pub Vec<Info>);
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Daily(pub Vec<DailyInfo>);
 
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Category(pub String);
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Info {
     pub top_10: Vec<u32>,
-    #[serde(default)] // actually nullable
-    pub top1_total: u32,
+    /// 無い場合はキー自体が存在しない
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top1_total: Option<u32>,
 
     pub sort: u32,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub user_id: Option<shop::UserId>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub shop_id: Option<shop::Id>,
     pub shop_name: shop::Name,
     pub area_id: area::Id,
     pub comment: String,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DailyInfo {
     pub pos_x: u32,
     pub pos_y: u32,
 
     pub point: u32,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub user_id: Option<shop::UserId>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub shop_id: Option<shop::Id>,
     pub shop_name: shop::Name,
     pub area_id: area::Id,
@@ -2,6 +2,7 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
+use super::trade::{Gold, Price, Units};
 use super::{area, item};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,9 +41,9 @@ pub struct ReportEntry {
     /// 総取引件数
     pub count: u64,
     /// 総取引数量
-    pub unit: u64,
+    pub unit: Units,
     /// 総取引額
-    pub money: u64,
+    pub money: Gold,
     /// 取引単価
-    pub price: u64,
+    pub price: Price,
 }
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 
+use super::trade::{Price, Units};
 use super::{area, item, shop};
 
 pub type Response = Vec<Request>;
@@ -9,24 +10,24 @@ pub type Response = Vec<Request>;
 /// 注文品
 pub struct Request {
     /// 注文通し番号
-    trans_serial: i64,
+    pub trans_serial: i64,
     /// 街ID
-    area_id: area::Id,
+    pub area_id: area::Id,
     /// オーナー番号
-    user_id: shop::UserId,
+    pub user_id: shop::UserId,
     /// ショップ番号
-    shop_id: shop::Id,
+    pub shop_id: shop::Id,
     /// ショップ名
-    shop_name: shop::Name,
+    pub shop_name: shop::Name,
     /// 商品ID
-    item_id: item::Id,
+    pub item_id: item::Id,
     /// 買い取り済み数量
-    unit: i32,
+    pub unit: Units,
     /// 買い付け希望数
-    buy_unit: i32,
+    pub buy_unit: Units,
     /// 注文単価
-    price: i32,
+    pub price: Price,
     /// 注文対象範囲
     #[serde(with = "area::serde_id_opt")]
-    request_area_id: Option<area::Id>,
+    pub request_area_id: Option<area::Id>,
 }
@@ -1,33 +1,127 @@
 use std::fmt::{Display, Formatter, Result};
 
 use chrono::{DateTime, NaiveDateTime};
-use serde::Deserialize;
+use serde::de::{self, IgnoredAny, SeqAccess, Visitor};
+use serde::ser::SerializeTuple;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+use super::trade::{Price, Units};
 use super::{item, shop};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Response(pub Vec<RequestReport>);
 
-#[derive(Debug, Deserialize)]
-pub struct Amount(pub u32);
-
-#[derive(Debug, Deserialize)]
-pub struct Price(pub u32);
-
-#[derive(Debug, Deserialize)]
+/// 配列 (`[seller_shop_id, ..., timestamp]`) で渡ってくるので, 順番は [COLUMNS] の通り
+#[derive(Debug, Clone)]
 pub struct RequestReport {
-    // 配列で渡ってくるので順番を変えないように
-    // 名前は変えてもいい
     pub seller_shop_id: shop::Id,
     pub seller_shop_name: shop::Name,
     pub buyer_shop_id: shop::Id,
     pub buyer_shop_name: shop::Name,
     pub item_id: item::Id,
-    pub item_count: Amount,
+    pub item_count: Units,
     pub order_price: Price,
     pub timestamp: i64,
 }
 
+/// [RequestReport] の列
+pub const COLUMNS: [&str; 8] = [
+    "seller_shop_id",
+    "seller_shop_name",
+    "buyer_shop_id",
+    "buyer_shop_name",
+    "item_id",
+    "item_count",
+    "order_price",
+    "timestamp",
+];
+
+impl Serialize for RequestReport {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut columns = serializer.serialize_tuple(COLUMNS.len())?;
+        columns.serialize_element(&self.seller_shop_id)?;
+        columns.serialize_element(&self.seller_shop_name)?;
+        columns.serialize_element(&self.buyer_shop_id)?;
+        columns.serialize_element(&self.buyer_shop_name)?;
+        columns.serialize_element(&self.item_id)?;
+        columns.serialize_element(&self.item_count)?;
+        columns.serialize_element(&self.order_price)?;
+        columns.serialize_element(&self.timestamp)?;
+        columns.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for RequestReport {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_seq(RequestReportVisitor)
+    }
+}
+
+struct RequestReportVisitor;
+
+impl RequestReportVisitor {
+    fn column<'de, T, A>(seq: &mut A, index: usize) -> std::result::Result<T, A::Error>
+    where
+        T: Deserialize<'de>,
+        A: SeqAccess<'de>,
+    {
+        let name = COLUMNS[index];
+        match seq.next_element() {
+            Ok(Some(value)) => Ok(value),
+            Ok(None) => Err(de::Error::custom(format_args!(
+                "missing column {index} ({name}), expected {} columns",
+                COLUMNS.len()
+            ))),
+            Err(e) => Err(de::Error::custom(format_args!(
+                "invalid column {index} ({name}): {e}"
+            ))),
+        }
+    }
+}
+
+impl<'de> Visitor<'de> for RequestReportVisitor {
+    type Value = RequestReport;
+
+    fn expecting(&self, f: &mut Formatter) -> Result {
+        write!(
+            f,
+            "an array of {} columns ({})",
+            COLUMNS.len(),
+            COLUMNS.join(", ")
+        )
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> std::result::Result<Self::Value, A::Error> {
+        let report = RequestReport {
+            seller_shop_id: Self::column(&mut seq, 0)?,
+            seller_shop_name: Self::column(&mut seq, 1)?,
+            buyer_shop_id: Self::column(&mut seq, 2)?,
+            buyer_shop_name: Self::column(&mut seq, 3)?,
+            item_id: Self::column(&mut seq, 4)?,
+            item_count: Self::column(&mut seq, 5)?,
+            order_price: Self::column(&mut seq, 6)?,
+            timestamp: Self::column(&mut seq, 7)?,
+        };
+
+        // サーバー側で列が増えた場合は位置がずれている可能性があるので読まない
+        let mut extra = 0;
+        while seq.next_element::<IgnoredAny>()?.is_some() {
+            extra += 1;
+        }
+        if extra > 0 {
+            return Err(de::Error::custom(format_args!(
+                "unexpected {extra} extra column(s), expected {} columns ({})",
+                COLUMNS.len(),
+                COLUMNS.join(", ")
+            )));
+        }
+        Ok(report)
+    }
+}
+
 impl RequestReport {
     pub fn traded_at(&self) -> NaiveDateTime {
         DateTime::from_timestamp(self.timestamp, 0)
@@ -46,15 +140,9 @@ impl Display for RequestReport {
             self.buyer_shop_name.0,
             self.buyer_shop_id,
             self.item_id.0,
-            self.item_count.0,
+            self.item_count,
             self.order_price,
             self.traded_at()
         )
     }
 }
-
-impl Display for Price {
-    fn fmt(&self, f: &mut Formatter) -> Result {
-        write!(f, "{}G", self.0)
-    }
-}
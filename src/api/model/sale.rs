@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
-use super::{item, shop};
+use super::trade::{Price, Units};
+use super::{area, item, shop};
 
 pub type Response = Vec<Sale>;
 
@@ -10,7 +11,7 @@ pub struct Sale {
     /// 販売通し番号
     pub sale_serial: i64,
     /// 街ID
-    pub area_id: i64,
+    pub area_id: area::Id,
     /// X座標
     pub pos_x: i64,
     /// Y座標
@@ -24,9 +25,9 @@ pub struct Sale {
     /// 商品ID
     pub item_id: item::Id,
     /// 販売単価
-    pub price: i64,
+    pub price: Price,
     /// 販売在庫数
-    pub unit: i64,
+    pub unit: Units,
     /// まとめ売り
     #[serde(with = "super::serde_bool_int")]
     pub bundle_sale: bool,
@@ -16,7 +16,7 @@ pub struct Shop {
     /// ショップ名
     pub shop_name: Name,
     /// キャッチコピー
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub comment: Option<String>,
     /// 街ID
     pub area_id: area::Id,
@@ -35,7 +35,7 @@ pub struct Shop {
     /// 創業日数
     pub foundation_days: i32,
     /// 元祖創業日数 (SO1データ引き継ぎをしていない場合は値自体が存在しません)
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub so1_foundation_days: Option<i32>,
     /// 商品図鑑登録数
     pub item_book: i32,
@@ -45,7 +45,7 @@ pub struct Shop {
     pub high_job: TitleJob,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Id(pub u32);
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -63,18 +63,34 @@ pub struct Level(pub u32);
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Name(pub String);
 
+/// `[id, level]`
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(into = "(ClassId, Level)")]
 pub struct TitleClass {
     pub id: ClassId,
     pub level: Level,
 }
 
+/// `[id, level]`
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(into = "(JobId, Level)")]
 pub struct TitleJob {
     pub id: JobId,
     pub level: Level,
 }
 
+impl From<TitleClass> for (ClassId, Level) {
+    fn from(title: TitleClass) -> Self {
+        (title.id, title.level)
+    }
+}
+
+impl From<TitleJob> for (JobId, Level) {
+    fn from(title: TitleJob) -> Self {
+        (title.id, title.level)
+    }
+}
+
 impl Display for Id {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         write!(f, "#{}", self.0)
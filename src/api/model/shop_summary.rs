@@ -1,17 +1,17 @@
 use std::fmt::{Display, Formatter, Result};
 
 use itertools::Itertools;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use super::area;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ShopSummary {
     pub total: ShopCount,
     pub areas: Vec<AreaShopSummary>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ShopCount(pub u32);
 
 impl Display for ShopCount {
@@ -20,7 +20,7 @@ impl Display for ShopCount {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AreaShopSummary {
     pub area_id: area::Id, // APIドキュメントだとstringになっているが?
     pub name: area::Name,
@@ -0,0 +1,113 @@
+//! 取引の単価, 金額, 数量
+//!
+//! 単価 × 数量 = 金額 ([Price] * [Units] = [Gold])
+//!
+//! サーバーが負の値を返さない保証は無いので符号付きで持ち, 演算は飽和させる
+
+use std::fmt::{Display, Formatter, Result};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Mul};
+
+use serde::{Deserialize, Serialize};
+
+/// 単価
+#[derive(
+    Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub struct Price(pub i64);
+
+/// 金額
+#[derive(
+    Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub struct Gold(pub i64);
+
+/// 数量
+#[derive(
+    Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub struct Units(pub i64);
+
+impl Mul<Units> for Price {
+    type Output = Gold;
+
+    fn mul(self, units: Units) -> Gold {
+        Gold(self.0.saturating_mul(units.0))
+    }
+}
+
+impl Mul<Price> for Units {
+    type Output = Gold;
+
+    fn mul(self, price: Price) -> Gold {
+        price * self
+    }
+}
+
+macro_rules! impl_sum {
+    ($($t:ident),+) => {
+        $(
+            impl Add for $t {
+                type Output = $t;
+
+                fn add(self, rhs: $t) -> $t {
+                    $t(self.0.saturating_add(rhs.0))
+                }
+            }
+
+            impl AddAssign for $t {
+                fn add_assign(&mut self, rhs: $t) {
+                    *self = *self + rhs;
+                }
+            }
+
+            impl Sum for $t {
+                fn sum<I: Iterator<Item = $t>>(iter: I) -> $t {
+                    iter.fold($t::default(), Add::add)
+                }
+            }
+        )+
+    };
+}
+
+impl_sum!(Gold, Units);
+
+impl Display for Price {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}G", self.0)
+    }
+}
+
+impl Display for Gold {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}G", self.0)
+    }
+}
+
+impl Display for Units {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negative_values_are_accepted() {
+        let price: Price = serde_json::from_str("-100").unwrap();
+        assert_eq!(price * Units(3), Gold(-300));
+        assert_eq!(serde_json::to_string(&price).unwrap(), "-100");
+    }
+
+    #[test]
+    fn arithmetic_saturates() {
+        assert_eq!(Price(i64::MAX) * Units(2), Gold(i64::MAX));
+        assert_eq!(Price(i64::MIN) * Units(2), Gold(i64::MIN));
+        assert_eq!(
+            [Units(i64::MAX), Units(1)].into_iter().sum::<Units>(),
+            Units(i64::MAX)
+        );
+    }
+}
@@ -1,29 +1,131 @@
-use std::{borrow::Cow, sync::LazyLock, time::Duration};
+use std::{sync::LazyLock, time::Duration};
 
-use chrono::{Datelike, NaiveDate};
+use chrono::{
+    DateTime, Datelike, FixedOffset, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Utc,
+};
+use error::InvalidParameter;
 use url::Url;
 
+use super::fragment;
+use super::model::ranking::EPOCH;
 use super::model::*;
 
 const DEFAULT_INTERVAL: Duration = Duration::from_secs(3600);
 
+/// 期間の区切りからサーバー側で集計が反映されるまでの猶予
+pub(crate) const PUBLISH_DELAY: TimeDelta = TimeDelta::hours(1);
+
+/// サーバーの時刻 (日本時間), 日付や時間帯の区切りはこの時刻による
+pub const SERVER_OFFSET: FixedOffset = FixedOffset::east_opt(9 * 3600).unwrap();
+
+/// `t` をサーバーの時刻で表したもの
+pub fn server_time(t: impl Into<DateTime<Utc>>) -> NaiveDateTime {
+    t.into().with_timezone(&SERVER_OFFSET).naive_local()
+}
+
 pub trait Schema {
-    type Response: for<'de> serde::Deserialize<'de>;
+    type Response: for<'de> serde::Deserialize<'de> + serde::Serialize;
 
-    fn endpoint(&self) -> Url;
+    /// [ORIGIN] からの相対パス
+    fn path(&self) -> String;
+
+    fn endpoint(&self, origin: &Url) -> Url {
+        origin.join(&self.path()).expect("invalid endpoint path")
+    }
 
     fn min_interval() -> Duration {
         DEFAULT_INTERVAL
     }
 
-    fn formatter(&self) -> Formatter {
-        Formatter::default()
+    fn format() -> Format {
+        Format::default()
+    }
+
+    /// 取得できるようになる時刻, `None` の場合はいつでも取得できる
+    fn published_at(&self) -> Option<NaiveDateTime> {
+        None
+    }
+
+    /// パラメータが取得できる範囲にあるか
+    fn validate(&self) -> Result<(), InvalidParameter> {
+        check_published(self.published_at())
+    }
+}
+
+fn check_published(published_at: Option<NaiveDateTime>) -> Result<(), InvalidParameter> {
+    match published_at {
+        Some(t) if server_time(Utc::now()) < t => Err(InvalidParameter::NotPublished(t)),
+        _ => Ok(()),
+    }
+}
+
+/// 期間毎のスキーマ用, 公開時刻が計算できない (日付が範囲外) 場合は取得できない
+fn check_period(published_at: Option<NaiveDateTime>) -> Result<(), InvalidParameter> {
+    match published_at {
+        Some(_) => check_published(published_at),
+        None => Err(InvalidParameter::OutOfRange),
+    }
+}
+
+fn check_date(date: NaiveDate) -> Result<(), InvalidParameter> {
+    match date < EPOCH {
+        true => Err(InvalidParameter::BeforeEpoch(date)),
+        false => Ok(()),
+    }
+}
+
+/// [EPOCH] を含む月は取得できる
+fn check_month(ym: NaiveDate) -> Result<(), InvalidParameter> {
+    match start_of_month(ym) < start_of_month(EPOCH) {
+        true => Err(InvalidParameter::BeforeEpoch(ym)),
+        false => Ok(()),
+    }
+}
+
+fn check_section(section: &str) -> Result<(), InvalidParameter> {
+    let valid = (!section.is_empty())
+        && (section.bytes()).all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-');
+    match valid {
+        true => Ok(()),
+        false => Err(InvalidParameter::Section(section.to_string())),
     }
 }
 
-macro_rules! impl_endpoint {
-    (|$self:ident| $($ep:tt)+) => { fn endpoint($self:&Self) -> Url { $($ep)+ } };
-    ($($ep:stmt)+) => { impl_endpoint!{ |self| $($ep)+ } };
+pub(crate) fn start_of_day(date: NaiveDate) -> NaiveDateTime {
+    date.and_time(NaiveTime::MIN)
+}
+
+pub(crate) fn start_of_hour(date: NaiveDate, hour: u8) -> Option<NaiveDateTime> {
+    Some(date.and_time(NaiveTime::from_hms_opt(hour.into(), 0, 0)?))
+}
+
+pub(crate) fn start_of_month(ym: NaiveDate) -> NaiveDateTime {
+    start_of_day(ym.with_day(1).unwrap_or(ym))
+}
+
+/// `start` から始まる期間の集計が反映される時刻
+fn after_delay(start: NaiveDateTime) -> Option<NaiveDateTime> {
+    start.checked_add_signed(PUBLISH_DELAY)
+}
+
+/// その日の集計が反映される時刻 (サーバーの時刻)
+pub(crate) fn end_of_day(date: NaiveDate) -> Option<NaiveDateTime> {
+    after_delay(start_of_day(date.succ_opt()?))
+}
+
+/// その時間の集計が反映される時刻 (サーバーの時刻)
+pub(crate) fn end_of_hour(date: NaiveDate, hour: u8) -> Option<NaiveDateTime> {
+    after_delay(start_of_hour(date, hour)?.checked_add_signed(TimeDelta::hours(1))?)
+}
+
+/// その月の集計が反映される時刻 (サーバーの時刻)
+pub(crate) fn end_of_month(ym: NaiveDate) -> Option<NaiveDateTime> {
+    after_delay(start_of_month(ym).checked_add_months(Months::new(1))?)
+}
+
+macro_rules! impl_path {
+    (|$self:ident| $($ep:tt)+) => { fn path($self:&Self) -> String { String::from({ $($ep)+ }) } };
+    ($($ep:stmt)+) => { impl_path!{ |self| $($ep)+ } };
 }
 
 macro_rules! impl_schema {
@@ -33,7 +135,7 @@ macro_rules! impl_schema {
         $(
             impl Schema for $self {
                 type Response = $res;
-                impl_endpoint!{ $($ep)+ }
+                impl_path!{ $($ep)+ }
                 $( $($other_impls)* )?
             }
         )+
@@ -41,38 +143,51 @@ macro_rules! impl_schema {
 }
 
 ///商品定義
+#[derive(Debug, Clone)]
 pub struct OfficialItem;
 ///レシピ商品定義
+#[derive(Debug, Clone)]
 pub struct RecipeItem;
 ///街定義
+#[derive(Debug, Clone)]
 pub struct Area;
 //.レポート
+#[derive(Debug, Clone)]
 pub struct Report(pub NaiveDate);
 ///ランキング/月間全部門トップ3
+#[derive(Debug, Clone)]
 pub struct RankingAllMonthly {
     pub ym: NaiveDate,
 }
 ///ランキング/月間部門別トップ1000
+#[derive(Debug, Clone)]
 pub struct RankingSectionMonthly {
     pub ym: NaiveDate,
     pub section: String,
 }
 ///ランキング/デイリートップ1000
+#[derive(Debug, Clone)]
 pub struct RankingSectionDaily {
     pub date: NaiveDate,
     pub section: String,
 }
 ///販売品
+#[derive(Debug, Clone)]
 pub struct Sale;
 ///注文品
+#[derive(Debug, Clone)]
 pub struct Request;
 ///お店件数
+#[derive(Debug, Clone)]
 pub struct ShopSummary;
 ///全お店リスト
+#[derive(Debug, Clone)]
 pub struct Shop;
 //.住民
+#[derive(Debug, Clone)]
 pub struct People;
 ///注文レポート
+#[derive(Debug, Clone)]
 pub enum RequestReport {
     ///全注文
     All { date: NaiveDate, hour: u8 },
@@ -80,51 +195,143 @@ pub enum RequestReport {
     Shop { date: NaiveDate, shop_id: shop::Id },
 }
 ///街情報
+#[derive(Debug, Clone)]
 pub struct AreaSummary;
 
-static ORIGIN: LazyLock<Url> = LazyLock::new(|| Url::parse("https://so2-api.mutoys.com").unwrap());
+impl Report {
+    /// 前日分は毎日0時過ぎに公開される
+    pub fn new(date: NaiveDate) -> Result<Self, InvalidParameter> {
+        validated(Self(date))
+    }
+}
+
+impl RankingAllMonthly {
+    /// 当月分は月初から公開され, 月末まで更新される
+    pub fn new(ym: NaiveDate) -> Result<Self, InvalidParameter> {
+        validated(Self { ym })
+    }
+}
+
+impl RankingSectionMonthly {
+    pub fn new(ym: NaiveDate, section: impl Into<String>) -> Result<Self, InvalidParameter> {
+        let section = section.into();
+        validated(Self { ym, section })
+    }
+}
+
+impl RankingSectionDaily {
+    /// 当日分は0時過ぎから公開され, 日付が変わるまで更新される
+    pub fn new(date: NaiveDate, section: impl Into<String>) -> Result<Self, InvalidParameter> {
+        let section = section.into();
+        validated(Self { date, section })
+    }
+}
+
+impl RequestReport {
+    /// `hour` 時台の分はその時間帯が終わってから公開される
+    pub fn all(date: NaiveDate, hour: u8) -> Result<Self, InvalidParameter> {
+        validated(Self::All { date, hour })
+    }
+
+    /// 当日分は0時過ぎから公開され, 日付が変わるまで更新される
+    pub fn shop(date: NaiveDate, shop_id: shop::Id) -> Result<Self, InvalidParameter> {
+        validated(Self::Shop { date, shop_id })
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        match self {
+            RequestReport::All { date, .. } | RequestReport::Shop { date, .. } => *date,
+        }
+    }
+}
+
+fn validated<S: Schema>(schema: S) -> Result<S, InvalidParameter> {
+    schema.validate().map(|()| schema)
+}
+
+/// 既定の接続先
+pub static ORIGIN: LazyLock<Url> =
+    LazyLock::new(|| Url::parse("https://so2-api.mutoys.com").unwrap());
 
 impl_schema! {
-    OfficialItem => item::Official { ORIGIN.join("master/item.json").unwrap() }
-    RecipeItem => item::Recipe { ORIGIN.join("json/master/recipe_item.json").unwrap() }
-    Area => area::Response { ORIGIN.join("master/area.json").unwrap() }
+    OfficialItem => item::Official { "master/item.json" }
+    RecipeItem => item::Recipe { "json/master/recipe_item.json" }
+    Area => area::Response { "master/area.json" }
     Report => report::Response { |self|
         let yyyy = self.0.year();
         let mm = self.0.month();
         let dd = self.0.day();
-        ORIGIN.join(&format!("json/report/buy{yyyy:04}{mm:02}{dd:02}.json")).unwrap()
+        format!("json/report/buy{yyyy:04}{mm:02}{dd:02}.json")
+    } {
+        fn published_at(&self) -> Option<NaiveDateTime> {
+            end_of_day(self.0)
+        }
+
+        fn validate(&self) -> Result<(), InvalidParameter> {
+            check_date(self.0)?;
+            check_period(self.published_at())
+        }
     }
     RankingAllMonthly => ranking::AllMonthly { |self|
         let yyyy = self.ym.year();
         let mm = self.ym.month();
-        ORIGIN.join(&format!("json/ranking/{yyyy:04}-{mm:02}/summary.json")).unwrap()
+        format!("json/ranking/{yyyy:04}-{mm:02}/summary.json")
+    } {
+        fn published_at(&self) -> Option<NaiveDateTime> {
+            after_delay(start_of_month(self.ym))
+        }
+
+        fn validate(&self) -> Result<(), InvalidParameter> {
+            check_month(self.ym)?;
+            check_period(self.published_at())
+        }
     }
     RankingSectionMonthly => ranking::SectionMonthly { |self|
         let yyyy = self.ym.year();
         let mm = self.ym.month();
         let section = &self.section;
-        ORIGIN.join(&format!("json/ranking/{yyyy:04}-{mm:02}/{section}.json")).unwrap()
+        format!("json/ranking/{yyyy:04}-{mm:02}/{section}.json")
+    } {
+        fn published_at(&self) -> Option<NaiveDateTime> {
+            after_delay(start_of_month(self.ym))
+        }
+
+        fn validate(&self) -> Result<(), InvalidParameter> {
+            check_month(self.ym)?;
+            check_section(&self.section)?;
+            check_period(self.published_at())
+        }
     }
     RankingSectionDaily => ranking::Daily { |self|
         let yyyy = self.date.year();
         let mm = self.date.month();
         let dd = self.date.day();
         let section = &self.section;
-        ORIGIN.join(&format!("json/ranking/{yyyy:04}-{mm:02}-{dd:02}/{section}.json")).unwrap()
+        format!("json/ranking/{yyyy:04}-{mm:02}-{dd:02}/{section}.json")
+    } {
+        fn published_at(&self) -> Option<NaiveDateTime> {
+            after_delay(start_of_day(self.date))
+        }
+
+        fn validate(&self) -> Result<(), InvalidParameter> {
+            check_date(self.date)?;
+            check_section(&self.section)?;
+            check_period(self.published_at())
+        }
     }
-    Sale => sale::Response { ORIGIN.join("json/sale/all.json").unwrap() } {
+    Sale => sale::Response { "json/sale/all.json" } {
         fn min_interval() -> Duration {
             Duration::from_secs(600)
         }
     }
-    Request => request::Response { ORIGIN.join("json/request/all.json").unwrap() } {
+    Request => request::Response { "json/request/all.json" } {
         fn min_interval() -> Duration {
             Duration::from_secs(600)
         }
     }
-    ShopSummary => shop_summary::ShopSummary { ORIGIN.join("json/shop/summary.json").unwrap() }
-    Shop => shop::Response { ORIGIN.join("json/shop/all.json").unwrap() }
-    People => people::Response { ORIGIN.join("json/people/all.json").unwrap() } {
+    ShopSummary => shop_summary::ShopSummary { "json/shop/summary.json" }
+    Shop => shop::Response { "json/shop/all.json" }
+    People => people::Response { "json/people/all.json" } {
         fn min_interval() -> Duration {
             Duration::from_secs(600)
         }
@@ -141,37 +348,238 @@ impl_schema! {
         let yyyy = date.year();
         let mm = date.month();
         let dd = date.day();
-        ORIGIN.join(&format!("json/request/{yyyy:04}/{mm:02}/{dd:02}/{arg}.json")).unwrap()
+        format!("json/request/{yyyy:04}/{mm:02}/{dd:02}/{arg}.json")
     }
     {
-        fn formatter(&self) -> Formatter {
-            Formatter::Special
+        fn format() -> Format {
+            Format::Fragments
+        }
+
+        fn published_at(&self) -> Option<NaiveDateTime> {
+            let start = match self {
+                RequestReport::All { date, hour } => start_of_hour(*date, *hour)?,
+                RequestReport::Shop { date, .. } => start_of_day(*date),
+            };
+            after_delay(start)
+        }
+
+        fn validate(&self) -> Result<(), InvalidParameter> {
+            if let RequestReport::All { hour, .. } = self {
+                if 24 <= *hour {
+                    return Err(InvalidParameter::Hour(*hour));
+                }
+            }
+            check_date(self.date())?;
+            check_period(self.published_at())
         }
     }
-    AreaSummary => area_summary::Response { ORIGIN.join("json/area/summary.json").unwrap() }
+    AreaSummary => area_summary::Response { "json/area/summary.json" }
+}
+
+/// 応答がJSON配列で, 要素を1つずつ読めるもの (see: [stream](super::stream))
+pub trait Streamable: Schema {
+    type Item: serde::de::DeserializeOwned + Send + 'static;
+}
+
+macro_rules! impl_streamable {
+    ( $( $self:ty => $item:ty ),+ $(,)? ) => {
+        $( impl Streamable for $self { type Item = $item; } )+
+    };
+}
+
+impl_streamable! {
+    Sale => sale::Sale,
+    Request => request::Request,
+    Shop => shop::Shop,
+    People => people::People,
 }
 
-#[derive(Debug, Default, Clone, Copy)]
-pub enum Formatter {
+/// 応答の形式
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
     #[default]
-    None,
-    Special,
+    Json,
+    /// 配列の要素だけをカンマ区切りで並べたもの (see: [fragment])
+    Fragments,
 }
 
-impl Formatter {
-    pub fn format<'a>(&self, bytes: &'a [u8]) -> Cow<'a, [u8]> {
+impl Format {
+    /// 失敗した場合は失敗箇所のJSONパス (e.g. `[12].shop_id`) を添えて返す
+    pub fn decode<T>(&self, bytes: &[u8]) -> Result<T, (String, serde_json::Error)>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        let result = match self {
+            Format::Json => {
+                let deserializer = &mut serde_json::Deserializer::from_slice(bytes);
+                serde_path_to_error::deserialize(deserializer)
+            }
+            Format::Fragments => serde_path_to_error::deserialize(fragment::Fragments::new(bytes)),
+        };
+        result.map_err(|e| {
+            let json_path = e.path().to_string();
+            (json_path, e.into_inner())
+        })
+    }
+
+    /// [decode](Self::decode) の逆, サーバーの応答と同じ形式で書き出す
+    pub fn encode<T: serde::Serialize>(&self, value: &T) -> Result<Vec<u8>, serde_json::Error> {
         match self {
-            Formatter::None => Cow::Borrowed(bytes),
-            Formatter::Special => {
-                let mut vec = Vec::with_capacity(bytes.len() + 2);
-                vec.push(b'[');
-                vec.extend_from_slice(bytes);
-                if vec.last() == Some(&b',') {
-                    vec.pop();
+            Format::Json => serde_json::to_vec(value),
+            Format::Fragments => fragment::to_vec(value),
+        }
+    }
+}
+
+pub mod error {
+    use std::error::Error;
+    use std::fmt::{Display, Formatter, Result};
+
+    use chrono::{NaiveDate, NaiveDateTime};
+
+    use crate::api::model::ranking::EPOCH;
+
+    /// 取得できないパラメータ
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum InvalidParameter {
+        /// [EPOCH] より前
+        BeforeEpoch(NaiveDate),
+        /// まだ公開されていない (公開予定の時刻)
+        NotPublished(NaiveDateTime),
+        /// 部門が空, またはパスに使えない文字を含む
+        Section(String),
+        /// 0-23 の範囲外
+        Hour(u8),
+        /// 日付が扱える範囲外
+        OutOfRange,
+    }
+
+    impl Display for InvalidParameter {
+        fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+            match self {
+                InvalidParameter::BeforeEpoch(date) => {
+                    write!(f, "{} is before {}", date, EPOCH)
                 }
-                vec.push(b']');
-                Cow::Owned(vec)
+                InvalidParameter::NotPublished(t) => write!(f, "not published until {}", t),
+                InvalidParameter::Section(section) => write!(f, "invalid section: {:?}", section),
+                InvalidParameter::Hour(hour) => write!(f, "invalid hour: {}", hour),
+                InvalidParameter::OutOfRange => write!(f, "date out of range"),
             }
         }
     }
+
+    impl Error for InvalidParameter {}
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Days, Timelike};
+
+    use super::*;
+
+    fn ymd(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn at(date: NaiveDate, h: u32) -> NaiveDateTime {
+        date.and_hms_opt(h, 0, 0).unwrap()
+    }
+
+    fn now() -> NaiveDateTime {
+        server_time(Utc::now())
+    }
+
+    #[test]
+    fn publish_times() {
+        let date = ymd(2024, 2, 29);
+        assert_eq!(Report(date).published_at(), Some(at(ymd(2024, 3, 1), 1)));
+        let ym = RankingAllMonthly { ym: date };
+        assert_eq!(ym.published_at(), Some(at(ymd(2024, 2, 1), 1)));
+        let all = RequestReport::All { date, hour: 23 };
+        assert_eq!(all.published_at(), Some(at(date, 23) + PUBLISH_DELAY));
+        assert_eq!(end_of_hour(date, 23), Some(at(ymd(2024, 3, 1), 1)));
+        assert_eq!(
+            end_of_month(ymd(2024, 12, 31)),
+            Some(at(ymd(2025, 1, 1), 1))
+        );
+    }
+
+    #[test]
+    fn before_epoch() {
+        let before = EPOCH.pred_opt().unwrap();
+        assert_eq!(
+            Report::new(before).unwrap_err(),
+            InvalidParameter::BeforeEpoch(before)
+        );
+        assert!(Report::new(EPOCH).is_ok());
+        // EPOCH を含む月は取得できる
+        assert!(RankingAllMonthly::new(ymd(2017, 5, 1)).is_ok());
+        assert!(matches!(
+            RankingAllMonthly::new(ymd(2017, 4, 30)),
+            Err(InvalidParameter::BeforeEpoch(_))
+        ));
+        assert!(matches!(
+            RequestReport::all(before, 0),
+            Err(InvalidParameter::BeforeEpoch(_))
+        ));
+    }
+
+    #[test]
+    fn not_published_yet() {
+        let now = now();
+        let today = now.date();
+        assert!(matches!(
+            Report::new(today),
+            Err(InvalidParameter::NotPublished(_))
+        ));
+        assert!(Report::new(today - Days::new(2)).is_ok());
+
+        let hour = now.hour() as u8;
+        assert!(matches!(
+            RequestReport::all(today, hour),
+            Err(InvalidParameter::NotPublished(_))
+        ));
+        let earlier = now - TimeDelta::hours(2);
+        assert!(RequestReport::all(earlier.date(), earlier.hour() as u8).is_ok());
+
+        let tomorrow = today + Days::new(1);
+        assert!(matches!(
+            RankingSectionDaily::new(tomorrow, "sales"),
+            Err(InvalidParameter::NotPublished(_))
+        ));
+    }
+
+    #[test]
+    fn out_of_range() {
+        assert_eq!(
+            Report::new(NaiveDate::MAX).unwrap_err(),
+            InvalidParameter::OutOfRange
+        );
+        assert_eq!(
+            RequestReport::all(NaiveDate::MAX, 23).unwrap_err(),
+            InvalidParameter::OutOfRange
+        );
+    }
+
+    #[test]
+    fn invalid_hour() {
+        assert_eq!(
+            RequestReport::all(EPOCH, 24).unwrap_err(),
+            InvalidParameter::Hour(24)
+        );
+        assert!(RequestReport::all(EPOCH, 23).is_ok());
+    }
+
+    #[test]
+    fn invalid_section() {
+        let ym = ymd(2020, 1, 1);
+        for section in ["", "a/b", "..", "a b"] {
+            assert_eq!(
+                RankingSectionMonthly::new(ym, section).unwrap_err(),
+                InvalidParameter::Section(section.to_string())
+            );
+        }
+        assert!(RankingSectionMonthly::new(ym, "exp_62").is_ok());
+        assert!(RankingSectionDaily::new(ym, "item-1").is_ok());
+    }
 }
@@ -0,0 +1,360 @@
+//! JSON配列の要素を全体を読み込まずに1つずつ読む
+//!
+//! 入力は任意の位置で区切ったチャンク (通信の受信単位やファイルの読み込み単位) で与える
+
+use std::io::Read;
+use std::marker::PhantomData;
+
+use serde::de::{DeserializeOwned, Error as _};
+use serde_json::Error;
+
+use error::ReadError;
+
+const UTF8_BOM: [u8; 3] = [0xEF, 0xBB, 0xBF];
+
+/// 失敗箇所のJSONパス (e.g. `[12].shop_id`) と原因
+pub type ElementError = (String, Error);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// `[` の前
+    Start,
+    /// 要素の間
+    Between,
+    /// 要素の途中, `depth` は開いている括弧の数
+    Element {
+        depth: usize,
+        in_string: bool,
+        escaped: bool,
+    },
+    /// `]` の後
+    End,
+    /// 形式が壊れていた
+    Failed,
+}
+
+/// チャンクを受け取って, 完結した要素から順に返す
+///
+/// 保持するのは読みかけの要素1つ分だけ
+#[derive(Debug)]
+pub struct Elements<T> {
+    state: State,
+    buf: Vec<u8>,
+    /// 読み終えた要素の数
+    index: usize,
+    /// 読み飛ばしたBOMの長さ
+    bom: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for Elements<T> {
+    fn default() -> Self {
+        Self {
+            state: State::Start,
+            buf: Vec::new(),
+            index: 0,
+            bom: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Elements<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `chunk` までで完結した要素
+    ///
+    /// 形式が壊れている場合は以降の入力を無視する
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<Result<T, ElementError>> {
+        let mut elements = Vec::new();
+        for &b in chunk {
+            if let Err(e) = self.step(b, &mut elements) {
+                elements.push(Err(e));
+                self.state = State::Failed;
+                self.buf.clear();
+                break;
+            }
+        }
+        elements
+    }
+
+    /// 入力が `]` で終わっているか (壊れていた場合は [feed](Self::feed) で返したので `Ok`)
+    pub fn finish(&self) -> Result<(), ElementError> {
+        match self.state {
+            State::End | State::Failed => Ok(()),
+            _ => Err((self.path(), Error::custom("unexpected end of array"))),
+        }
+    }
+
+    fn step(
+        &mut self,
+        b: u8,
+        elements: &mut Vec<Result<T, ElementError>>,
+    ) -> Result<(), ElementError> {
+        match self.state {
+            State::Start if self.bom < UTF8_BOM.len() && b == UTF8_BOM[self.bom] => self.bom += 1,
+            State::Start if b.is_ascii_whitespace() => {}
+            State::Start if b == b'[' => self.state = State::Between,
+            State::Start => return Err((".".to_string(), Error::custom("expected `[`"))),
+            State::Between if b.is_ascii_whitespace() || b == b',' => {}
+            State::Between if b == b']' => self.state = State::End,
+            State::Between => {
+                self.state = State::Element {
+                    depth: 0,
+                    in_string: false,
+                    escaped: false,
+                };
+                self.step(b, elements)?;
+            }
+            State::Element {
+                depth,
+                in_string: true,
+                escaped,
+            } => {
+                self.buf.push(b);
+                let in_string = escaped || b != b'"';
+                let escaped = !escaped && b == b'\\';
+                if !in_string && depth == 0 {
+                    elements.push(self.complete());
+                } else {
+                    self.state = State::Element {
+                        depth,
+                        in_string,
+                        escaped,
+                    };
+                }
+            }
+            State::Element { depth, .. } => match b {
+                // 数値などの末尾は区切りで分かる
+                b',' | b']' if depth == 0 => {
+                    elements.push(self.complete());
+                    if b == b']' {
+                        self.state = State::End;
+                    }
+                }
+                _ if depth == 0 && b.is_ascii_whitespace() => elements.push(self.complete()),
+                b'}' if depth == 0 => return Err((self.path(), Error::custom("unexpected `}`"))),
+                _ => {
+                    self.buf.push(b);
+                    let depth = match b {
+                        b'[' | b'{' => depth + 1,
+                        b']' | b'}' => depth - 1,
+                        _ => depth,
+                    };
+                    if depth == 0 && matches!(b, b']' | b'}') {
+                        elements.push(self.complete());
+                    } else {
+                        self.state = State::Element {
+                            depth,
+                            in_string: b == b'"',
+                            escaped: false,
+                        };
+                    }
+                }
+            },
+            State::End if b.is_ascii_whitespace() => {}
+            State::End => return Err((".".to_string(), Error::custom("trailing characters"))),
+            State::Failed => {}
+        }
+        Ok(())
+    }
+
+    fn complete(&mut self) -> Result<T, ElementError> {
+        let deserializer = &mut serde_json::Deserializer::from_slice(&self.buf);
+        let result = serde_path_to_error::deserialize(deserializer).map_err(|e| {
+            let json_path = match e.path().to_string() {
+                path if path == "." => self.path(),
+                path if path.starts_with('[') => format!("{}{path}", self.path()),
+                path => format!("{}.{path}", self.path()),
+            };
+            (json_path, e.into_inner())
+        });
+        self.buf.clear();
+        self.index += 1;
+        self.state = State::Between;
+        result
+    }
+
+    fn path(&self) -> String {
+        format!("[{}]", self.index)
+    }
+}
+
+/// [Read] から要素を1つずつ読む
+#[derive(Debug)]
+pub struct ReadElements<R, T> {
+    reader: R,
+    elements: Elements<T>,
+    pending: std::vec::IntoIter<Result<T, ElementError>>,
+    chunk: Box<[u8]>,
+    done: bool,
+}
+
+impl<R: Read, T: DeserializeOwned> ReadElements<R, T> {
+    const CHUNK_SIZE: usize = 64 * 1024;
+
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            elements: Elements::new(),
+            pending: Vec::new().into_iter(),
+            chunk: vec![0; Self::CHUNK_SIZE].into_boxed_slice(),
+            done: false,
+        }
+    }
+}
+
+impl<R: Read, T: DeserializeOwned> Iterator for ReadElements<R, T> {
+    type Item = Result<T, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(element) = self.pending.next() {
+                return Some(element.map_err(ReadError::Element));
+            }
+            if self.done {
+                return None;
+            }
+            match self.reader.read(&mut self.chunk) {
+                Ok(0) => {
+                    self.done = true;
+                    return self
+                        .elements
+                        .finish()
+                        .err()
+                        .map(|e| Err(ReadError::Element(e)));
+                }
+                Ok(n) => self.pending = self.elements.feed(&self.chunk[..n]).into_iter(),
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(ReadError::Io(e)));
+                }
+            }
+        }
+    }
+}
+
+pub mod error {
+    use std::error::Error;
+    use std::fmt::{Display, Formatter, Result};
+
+    use super::ElementError;
+
+    /// [ReadElements](super::ReadElements) の失敗
+    #[derive(Debug)]
+    pub enum ReadError {
+        Io(std::io::Error),
+        Element(ElementError),
+    }
+
+    impl Display for ReadError {
+        fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+            match self {
+                ReadError::Io(e) => write!(f, "{}", e),
+                ReadError::Element((json_path, e)) => write!(f, "{} at {}", e, json_path),
+            }
+        }
+    }
+
+    impl Error for ReadError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            match self {
+                ReadError::Io(e) => Some(e),
+                ReadError::Element((_, e)) => Some(e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    const INPUTS: &[&str] = &[
+        "[]",
+        " [ ] ",
+        "[1,-2.5e3,true,false,null]",
+        "[ 1 , 2\n,\t3 ]\n",
+        r#"["a","b\"c","d\\","e\\\"f","]","[{","あ","日本語"]"#,
+        r#"[{"a":[1,{"b":"}"}],"c":{}},[[],[[]]],{}]"#,
+        r#"[{"shop_id":1,"name":"x, y"},{"shop_id":2,"name":"[z]"}]"#,
+        "\u{FEFF}[1,{\"a\":2}]",
+    ];
+
+    fn feed_chunks(chunks: &[&[u8]]) -> Vec<Value> {
+        let mut elements = Elements::<Value>::new();
+        let mut values = Vec::new();
+        for chunk in chunks {
+            for element in elements.feed(chunk) {
+                values.push(element.unwrap());
+            }
+        }
+        elements.finish().unwrap();
+        values
+    }
+
+    fn expected(input: &str) -> Vec<Value> {
+        let json = input.trim_start_matches('\u{FEFF}');
+        serde_json::from_slice(json.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn split_at_every_boundary() {
+        for input in INPUTS {
+            let bytes = input.as_bytes();
+            let expected = expected(input);
+            for i in 0..=bytes.len() {
+                let (head, tail) = bytes.split_at(i);
+                assert_eq!(feed_chunks(&[head, tail]), expected, "{input:?} at {i}");
+            }
+        }
+    }
+
+    #[test]
+    fn one_byte_chunks() {
+        for input in INPUTS {
+            let chunks = input.as_bytes().chunks(1).collect::<Vec<_>>();
+            assert_eq!(feed_chunks(&chunks), expected(input), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn read_elements() {
+        for input in INPUTS {
+            let values = ReadElements::<_, Value>::new(input.as_bytes())
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(values, expected(input), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn element_error_has_path() {
+        let mut elements = Elements::<u32>::new();
+        let results = elements.feed(br#"[1, 2, "x", 4]"#);
+        assert_eq!(results.len(), 4);
+        let (path, _) = results[2].as_ref().unwrap_err();
+        assert_eq!(path, "[2]");
+        // 要素の失敗の後も続きは読める
+        assert_eq!(*results[3].as_ref().unwrap(), 4);
+        elements.finish().unwrap();
+    }
+
+    #[test]
+    fn malformed() {
+        let mut elements = Elements::<Value>::new();
+        assert!(elements.feed(b"{}").pop().unwrap().is_err());
+
+        let mut elements = Elements::<Value>::new();
+        assert!(elements.feed(b"[1] x").pop().unwrap().is_err());
+
+        let mut elements = Elements::<Value>::new();
+        assert!(elements.feed(b"[1, 2").iter().all(Result::is_ok));
+        assert!(elements.finish().is_err());
+    }
+}
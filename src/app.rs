@@ -1,3 +1,10 @@
+pub mod api_client;
 pub mod api_loader;
+pub mod archive;
+pub mod backfill;
 pub mod cache;
+pub mod config;
 pub mod delete_expired_cache;
+pub mod ranking_loader;
+pub mod registry;
+pub mod request_trades;
@@ -0,0 +1,602 @@
+use std::future::Future;
+use std::hash::{BuildHasher, RandomState};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+use futures::StreamExt;
+use futures::stream::BoxStream;
+use reqwest::StatusCode;
+use url::Url;
+
+use error::{FetchError, TransportError};
+
+use crate::api::schema::{ORIGIN, Schema};
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// 通信部分の抽象化
+///
+/// テストやミラーでは任意の実装に差し替える
+pub trait Fetch: Send + Sync {
+    fn fetch<'a>(
+        &'a self,
+        request: &'a FetchRequest,
+    ) -> BoxFuture<'a, Result<FetchResponse, TransportError>>;
+
+    /// 本体を受信した単位で返す, 既定では [fetch](Self::fetch) で全体を受信してから返す
+    fn fetch_stream<'a>(
+        &'a self,
+        request: &'a FetchRequest,
+    ) -> BoxFuture<'a, Result<FetchStream, TransportError>> {
+        Box::pin(async move {
+            let FetchResponse {
+                status,
+                headers,
+                body,
+            } = self.fetch(request).await?;
+            Ok(FetchStream {
+                status,
+                headers,
+                body: futures::stream::once(async { Ok(body) }).boxed(),
+            })
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FetchRequest {
+    pub url: Url,
+    /// 条件付きリクエスト用
+    pub validators: Validators,
+}
+
+/// 条件付きリクエスト (`If-None-Match` / `If-Modified-Since`) に使う値
+#[derive(Debug, Default, Clone)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FetchResponse {
+    pub status: StatusCode,
+    pub headers: ResponseHeaders,
+    pub body: Vec<u8>,
+}
+
+/// 本体を受信しながら読む応答
+pub struct FetchStream {
+    pub status: StatusCode,
+    pub headers: ResponseHeaders,
+    pub body: BoxStream<'static, Result<Vec<u8>, TransportError>>,
+}
+
+impl std::fmt::Debug for FetchStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FetchStream")
+            .field("status", &self.status)
+            .field("headers", &self.headers)
+            .finish_non_exhaustive()
+    }
+}
+
+/// キャッシュの管理に使う応答ヘッダ
+#[derive(Debug, Default, Clone)]
+pub struct ResponseHeaders {
+    pub date: Option<String>,
+    pub last_modified: Option<String>,
+    pub etag: Option<String>,
+}
+
+/// 条件付きリクエストの結果
+#[derive(Debug, Clone)]
+pub enum Fetched {
+    Modified {
+        headers: ResponseHeaders,
+        body: Vec<u8>,
+    },
+    /// 304 Not Modified
+    NotModified { headers: ResponseHeaders },
+}
+
+pub const USER_AGENT: &str = concat!(
+    env!("CARGO_PKG_NAME"),
+    "/",
+    env!("CARGO_PKG_VERSION"),
+    " (+https://github.com/falrnd/so2_tool)"
+);
+
+/// 全スキーマで共有する [reqwest::Client]
+static SHARED_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    ClientConfig::default()
+        .build()
+        .expect("failed to build default http client")
+});
+
+/// HTTPクライアントの設定
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    pub connect_timeout: Duration,
+    /// 受信が途切れてからの待ち時間
+    pub read_timeout: Duration,
+    /// リクエスト全体の上限
+    pub timeout: Option<Duration>,
+    pub user_agent: String,
+    pub gzip: bool,
+    /// `None` の場合は環境変数 (`HTTPS_PROXY` 等) に従う
+    pub proxy: Option<Url>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: Duration::from_secs(30),
+            timeout: Some(Duration::from_secs(120)),
+            user_agent: USER_AGENT.to_string(),
+            gzip: true,
+            proxy: None,
+        }
+    }
+}
+
+impl ClientConfig {
+    pub fn build(&self) -> Result<reqwest::Client, reqwest::Error> {
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(self.connect_timeout)
+            .read_timeout(self.read_timeout)
+            .user_agent(&self.user_agent)
+            .gzip(self.gzip);
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy.clone())?);
+        }
+        builder.build()
+    }
+}
+
+/// [reqwest] による通常の通信
+#[derive(Debug, Clone)]
+pub struct ReqwestFetch {
+    client: reqwest::Client,
+}
+
+impl ReqwestFetch {
+    pub fn new(config: &ClientConfig) -> Result<Self, reqwest::Error> {
+        Ok(Self {
+            client: config.build()?,
+        })
+    }
+
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+}
+
+impl Default for ReqwestFetch {
+    fn default() -> Self {
+        Self {
+            client: SHARED_CLIENT.clone(),
+        }
+    }
+}
+
+impl ReqwestFetch {
+    async fn send(
+        &self,
+        request: &FetchRequest,
+    ) -> Result<(ResponseHeaders, reqwest::Response), reqwest::Error> {
+        use reqwest::header::{DATE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+
+        let mut builder = self.client.get(request.url.clone());
+        if let Some(etag) = &request.validators.etag {
+            builder = builder.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &request.validators.last_modified {
+            builder = builder.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        let response = builder.send().await?;
+
+        let header = |name| {
+            let value = response.headers().get(name)?.to_str().ok()?;
+            Some(value.to_string())
+        };
+        let headers = ResponseHeaders {
+            date: header(DATE),
+            last_modified: header(LAST_MODIFIED),
+            etag: header(ETAG),
+        };
+        Ok((headers, response))
+    }
+}
+
+impl Fetch for ReqwestFetch {
+    fn fetch<'a>(
+        &'a self,
+        request: &'a FetchRequest,
+    ) -> BoxFuture<'a, Result<FetchResponse, TransportError>> {
+        Box::pin(async move {
+            let (headers, response) = self.send(request).await?;
+            let status = response.status();
+            let body = response.bytes().await?.to_vec();
+            Ok(FetchResponse {
+                status,
+                headers,
+                body,
+            })
+        })
+    }
+
+    fn fetch_stream<'a>(
+        &'a self,
+        request: &'a FetchRequest,
+    ) -> BoxFuture<'a, Result<FetchStream, TransportError>> {
+        Box::pin(async move {
+            let (headers, response) = self.send(request).await?;
+            let status = response.status();
+            // 失敗した後は終わる
+            let body = futures::stream::unfold(Some(response), |response| async move {
+                let mut response = response?;
+                match response.chunk().await {
+                    Ok(Some(chunk)) => Some((Ok(chunk.to_vec()), Some(response))),
+                    Ok(None) => None,
+                    Err(e) => Some((Err(e.into()), None)),
+                }
+            });
+            Ok(FetchStream {
+                status,
+                headers,
+                body: body.boxed(),
+            })
+        })
+    }
+}
+
+/// URLのパスをディレクトリ上のファイルに対応させる (オフライン用)
+#[derive(Debug, Clone)]
+pub struct LocalDirFetch {
+    pub root: PathBuf,
+}
+
+impl Fetch for LocalDirFetch {
+    fn fetch<'a>(
+        &'a self,
+        request: &'a FetchRequest,
+    ) -> BoxFuture<'a, Result<FetchResponse, TransportError>> {
+        Box::pin(async move {
+            let path = (request.url)
+                .path_segments()
+                .into_iter()
+                .flatten()
+                .fold(self.root.clone(), |path, segment| path.join(segment));
+            match std::fs::read(path) {
+                Ok(body) => Ok(FetchResponse {
+                    status: StatusCode::OK,
+                    headers: ResponseHeaders::default(),
+                    body,
+                }),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(FetchResponse {
+                    status: StatusCode::NOT_FOUND,
+                    headers: ResponseHeaders::default(),
+                    body: Vec::new(),
+                }),
+                Err(e) => Err(TransportError::permanent(e)),
+            }
+        })
+    }
+}
+
+/// 一時的な失敗に対する再試行の設定
+///
+/// n回目の再試行までの待ち時間は `base_delay * 2^(n-1)` (`max_delay` まで) に
+/// 0~50% のゆらぎを加えたもの
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub const NONE: Self = Self {
+        max_retries: 0,
+        base_delay: Duration::ZERO,
+        max_delay: Duration::ZERO,
+    };
+
+    fn delay(&self, retry: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(1 << (retry - 1).min(16))
+            .min(self.max_delay);
+        let jitter = RandomState::new().hash_one(retry) % 1000;
+        exp + exp.mul_f64(jitter as f64 / 2000.0)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+/// 接続先(origin)と通信手段の組
+#[derive(Clone)]
+pub struct ApiClient {
+    origin: Url,
+    transport: Arc<dyn Fetch>,
+    retry: RetryPolicy,
+}
+
+impl ApiClient {
+    pub fn new(origin: Url, transport: impl Fetch + 'static) -> Self {
+        Self {
+            origin: Self::as_base(origin),
+            transport: Arc::new(transport),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_origin(origin: Url) -> Self {
+        Self::new(origin, ReqwestFetch::default())
+    }
+
+    pub fn origin(&self) -> &Url {
+        &self.origin
+    }
+
+    pub fn endpoint<S: Schema>(&self, schema: &S) -> Url {
+        schema.endpoint(&self.origin)
+    }
+
+    pub fn retry(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    /// 2xx 以外は [FetchError::Status] とし, 一時的な失敗は [RetryPolicy] に従って再試行する
+    pub async fn fetch<S: Schema>(&self, schema: &S) -> Result<Vec<u8>, FetchError> {
+        match self
+            .fetch_conditional(schema, &Validators::default())
+            .await?
+        {
+            Fetched::Modified { body, .. } => Ok(body),
+            Fetched::NotModified { .. } => unreachable!("304 without validators"),
+        }
+    }
+
+    /// [fetch](Self::fetch) の条件付きリクエスト版, 304 は [Fetched::NotModified] になる
+    pub async fn fetch_conditional<S: Schema>(
+        &self,
+        schema: &S,
+        validators: &Validators,
+    ) -> Result<Fetched, FetchError> {
+        let request = FetchRequest {
+            url: self.endpoint(schema),
+            validators: validators.clone(),
+        };
+        let endpoint = &request.url;
+        self.retrying(endpoint, || async {
+            match self.transport.fetch(&request).await {
+                Ok(FetchResponse {
+                    status,
+                    headers,
+                    body,
+                }) if status.is_success() => Ok(Fetched::Modified { headers, body }),
+                Ok(FetchResponse {
+                    status: StatusCode::NOT_MODIFIED,
+                    headers,
+                    ..
+                }) if !validators.is_empty() => Ok(Fetched::NotModified { headers }),
+                Ok(response) => Err(FetchError::Status {
+                    url: endpoint.clone(),
+                    status: response.status,
+                }),
+                Err(e) => Err(FetchError::Transport {
+                    url: endpoint.clone(),
+                    source: e,
+                }),
+            }
+        })
+        .await
+    }
+
+    /// 本体を受信した単位で返す
+    ///
+    /// 再試行するのは応答の開始までで, 受信の途中で失敗した場合はそこで終わる
+    pub async fn fetch_stream<S: Schema>(
+        &self,
+        schema: &S,
+    ) -> Result<BoxStream<'static, Result<Vec<u8>, FetchError>>, FetchError> {
+        let request = FetchRequest {
+            url: self.endpoint(schema),
+            validators: Validators::default(),
+        };
+        let endpoint = &request.url;
+        let body = self
+            .retrying(endpoint, || async {
+                match self.transport.fetch_stream(&request).await {
+                    Ok(response) if response.status.is_success() => Ok(response.body),
+                    Ok(response) => Err(FetchError::Status {
+                        url: endpoint.clone(),
+                        status: response.status,
+                    }),
+                    Err(e) => Err(FetchError::Transport {
+                        url: endpoint.clone(),
+                        source: e,
+                    }),
+                }
+            })
+            .await?;
+        let url = endpoint.clone();
+        Ok(body
+            .map(move |chunk| {
+                chunk.map_err(|source| FetchError::Transport {
+                    url: url.clone(),
+                    source,
+                })
+            })
+            .boxed())
+    }
+
+    /// 一時的な失敗は [RetryPolicy] に従って再試行する
+    async fn retrying<T, F, Fut>(&self, endpoint: &Url, mut attempt: F) -> Result<T, FetchError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, FetchError>>,
+    {
+        let mut retry = 0;
+        loop {
+            println!("API call: {}", endpoint);
+            match attempt().await {
+                Err(e) if e.is_transient() && retry < self.retry.max_retries => {
+                    retry += 1;
+                    let delay = self.retry.delay(retry);
+                    eprintln!(
+                        "{e}, retry {retry}/{} after {delay:?}",
+                        self.retry.max_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // "http://host/mirror" の様な指定でも相対パスがその下に解決されるように
+    fn as_base(mut origin: Url) -> Url {
+        if !origin.path().ends_with('/') {
+            let path = format!("{}/", origin.path());
+            origin.set_path(&path);
+        }
+        origin
+    }
+}
+
+impl Default for ApiClient {
+    fn default() -> Self {
+        Self::with_origin(ORIGIN.clone())
+    }
+}
+
+impl std::fmt::Debug for ApiClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiClient")
+            .field("origin", &self.origin.as_str())
+            .field("retry", &self.retry)
+            .finish_non_exhaustive()
+    }
+}
+
+pub mod error {
+    use std::error::Error;
+    use std::fmt::{Display, Formatter, Result};
+
+    use reqwest::StatusCode;
+    use url::Url;
+
+    /// 通信そのものの失敗
+    #[derive(Debug)]
+    pub struct TransportError {
+        /// タイムアウトや接続断など, 再試行で回復しうるもの
+        pub transient: bool,
+        pub source: Box<dyn Error + Send + Sync>,
+    }
+
+    impl TransportError {
+        pub fn transient(e: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+            Self {
+                transient: true,
+                source: e.into(),
+            }
+        }
+
+        pub fn permanent(e: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+            Self {
+                transient: false,
+                source: e.into(),
+            }
+        }
+    }
+
+    impl From<reqwest::Error> for TransportError {
+        fn from(e: reqwest::Error) -> Self {
+            let transient = e.is_timeout() || e.is_connect() || e.is_request() || e.is_body();
+            Self {
+                transient,
+                source: e.into(),
+            }
+        }
+    }
+
+    impl Display for TransportError {
+        fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+            Display::fmt(&self.source, f)
+        }
+    }
+
+    impl Error for TransportError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&*self.source)
+        }
+    }
+
+    #[derive(Debug)]
+    pub enum FetchError {
+        Transport { url: Url, source: TransportError },
+        Status { url: Url, status: StatusCode },
+    }
+
+    impl FetchError {
+        pub fn is_transient(&self) -> bool {
+            match self {
+                FetchError::Transport { source, .. } => source.transient,
+                FetchError::Status { status, .. } => {
+                    status.is_server_error()
+                        || *status == StatusCode::REQUEST_TIMEOUT
+                        || *status == StatusCode::TOO_MANY_REQUESTS
+                }
+            }
+        }
+    }
+
+    impl Display for FetchError {
+        fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+            match self {
+                FetchError::Transport { url, source } => {
+                    write!(f, "Request failed: {} ({})", url, source)
+                }
+                FetchError::Status { url, status } => {
+                    write!(f, "Unexpected status: {} ({})", url, status)
+                }
+            }
+        }
+    }
+
+    impl Error for FetchError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            match self {
+                FetchError::Transport { source, .. } => Some(source),
+                FetchError::Status { .. } => None,
+            }
+        }
+    }
+}
@@ -1,18 +1,37 @@
-use std::borrow::Cow;
-use std::error::Error;
-use std::fs::File;
-use std::io::{BufReader, Write};
-use std::path::PathBuf;
-use std::time::SystemTime;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
-use error::CacheLoadError;
+use error::{CacheLoadError, LoaderError};
+use futures::StreamExt;
+use futures::stream::BoxStream;
 
-use crate::api::schema::Schema;
+use crate::api::schema::{Schema, Streamable};
+use crate::api::stream::error::ReadError;
+use crate::api::stream::{Elements, ReadElements};
+use crate::app::api_client::error::FetchError;
+use crate::app::api_client::{ApiClient, Fetched, ResponseHeaders, Validators};
+use crate::app::archive::{Archive, RetentionPolicy};
+use crate::app::cache::compression;
+use crate::app::cache::file::CacheLock;
+use crate::app::cache::memory::MEMORY;
+use crate::app::cache::meta::CacheMeta;
+use crate::app::cache::quarantine::Quarantine;
+use crate::app::cache::store::{self, CacheStore, Stored};
 use crate::app::cache::{Cacheable, DEFAULT_CACHE_ROOT};
 
+/// [APILoader::stream] の要素の並び
+pub type Items<T> = BoxStream<'static, Result<T, LoaderError>>;
+
+#[derive(Debug, Clone)]
 pub struct APILoader<S: Schema> {
     pub schema: S,
     pub cache_root: PathBuf,
+    pub client: ApiClient,
+    /// `Some` の場合, 取得した応答を [Archive] にも保存する
+    pub archive: Option<RetentionPolicy>,
+    /// `None` の場合は `cache_root` に設定された保存先 (see: [store::open])
+    pub store: Option<Arc<dyn CacheStore>>,
 }
 
 impl<S> APILoader<S>
@@ -20,9 +39,16 @@ where
     S: Schema,
 {
     pub fn new(schema: S) -> Self {
+        Self::with_client(schema, ApiClient::default())
+    }
+
+    pub fn with_client(schema: S, client: ApiClient) -> Self {
         Self {
             schema,
             cache_root: DEFAULT_CACHE_ROOT.to_path_buf(),
+            client,
+            archive: None,
+            store: None,
         }
     }
 
@@ -30,22 +56,55 @@ where
         &self.schema
     }
 
+    /// 設定 (接続先, 保存先など) を引き継いで別のスキーマを読み込む
+    pub fn with_schema<T: Schema>(&self, schema: T) -> APILoader<T> {
+        APILoader {
+            schema,
+            cache_root: self.cache_root.clone(),
+            client: self.client.clone(),
+            archive: self.archive,
+            store: self.store.clone(),
+        }
+    }
+
     pub fn set_cache_root(&mut self, cache_root: PathBuf) -> &mut Self {
         self.cache_root = cache_root;
         self
     }
 
-    fn cache_file_path(&self) -> PathBuf
+    pub fn set_client(&mut self, client: ApiClient) -> &mut Self {
+        self.client = client;
+        self
+    }
+
+    /// [Cacheable::archive_dir] が無いスキーマでは何もしない
+    pub fn set_archive(&mut self, retention: Option<RetentionPolicy>) -> &mut Self {
+        self.archive = retention;
+        self
+    }
+
+    /// 隔離やスナップショットは引き続き `cache_root` 以下に保存する
+    pub fn set_store(&mut self, store: Arc<dyn CacheStore>) -> &mut Self {
+        self.store = Some(store);
+        self
+    }
+
+    pub fn store(&self) -> Arc<dyn CacheStore> {
+        match &self.store {
+            Some(store) => Arc::clone(store),
+            None => store::open(&self.cache_root),
+        }
+    }
+
+    fn cache_key(&self) -> PathBuf
     where
         S: Cacheable,
     {
-        self.cache_root.join(self.schema.file_path())
+        self.schema.file_path()
     }
 
-    pub async fn call_api(&self) -> Result<reqwest::Response, reqwest::Error> {
-        let endpoint = self.schema.endpoint();
-        println!("API call: {}", endpoint);
-        reqwest::get(endpoint).await
+    pub async fn call_api(&self) -> Result<Vec<u8>, FetchError> {
+        self.client.fetch(&self.schema).await
     }
 
     pub fn load_cache(&self) -> Result<S::Response, CacheLoadError>
@@ -53,70 +112,529 @@ where
         S: Cacheable,
     {
         use CacheLoadError::*;
-        println!("Load cache: {:?}", self.cache_file_path());
-
-        let path = self.cache_file_path();
 
-        let file = File::open(&path).map_err(|e| FileNotFound(e.into()))?;
-        let time_stamp = get_timestamp(&file).map_err(|e| FileNotFound(e.into()))?;
+        let store = self.store();
+        let key = self.cache_key();
+        let stored = store
+            .read(&key)
+            .map_err(|e| match compression::is_corrupt(&e) {
+                true => ParseFailed {
+                    json_path: ".".to_string(),
+                    source: serde_json::Error::io(e),
+                },
+                false => FileNotFound(e),
+            })?
+            .ok_or_else(|| FileNotFound(std::io::ErrorKind::NotFound.into()))?;
+        let path = store.location(&key);
+        println!("Load cache: {:?}", path);
+        let time_stamp = self.cache_timestamp(&stored);
 
-        let cache_living = (time_stamp.elapsed()).is_ok_and(|t| t < S::min_interval());
+        let cache_living = time_stamp.is_some_and(|time_stamp| {
+            self.schema.is_finalized_since(time_stamp)
+                || (time_stamp.elapsed()).is_ok_and(|t| t < S::min_interval())
+        });
         if cache_living {
-            serde_json::from_reader(BufReader::new(file))
-                .map_err(|e| CacheLoadError::ParseFailed(e.into()))
+            (S::format().decode(&stored.bytes))
+                .map_err(|(json_path, source)| ParseFailed { json_path, source })
         } else {
             Err(CacheExpired {
+                updated: stored.fetched_at(),
                 path,
-                updated: time_stamp,
                 interval: S::min_interval(),
             })
         }
     }
 
-    pub fn save_cache<'a>(&self, api_call: &'a [u8]) -> Result<Cow<'a, [u8]>, Box<dyn Error>>
+    /// サーバーの応答をそのまま保存する
+    pub fn save_cache(&self, api_call: &[u8]) -> Result<(), LoaderError>
     where
         S: Cacheable,
     {
-        let cache_file_path = self.cache_file_path();
-        let _ = std::fs::create_dir_all(cache_file_path.parent().expect("invalid cache dir"));
-        let formatted = self.schema.formatter().format(api_call);
-        File::create(&cache_file_path)?.write_all(&formatted)?;
-        println!("Save cache: {:?}", cache_file_path);
-        Ok(formatted)
+        self.write_cache(api_call, ResponseHeaders::default())
     }
 
-    pub async fn get(&self) -> Result<S::Response, Box<dyn Error>>
+    /// キャッシュ本体とメタデータを保存する
+    fn write_cache(&self, bytes: &[u8], headers: ResponseHeaders) -> Result<(), LoaderError>
     where
         S: Cacheable,
     {
-        match self.load_cache() {
-            Ok(response) => return Ok(response),
-            Err(e) => {
-                if let CacheLoadError::ParseFailed(_) = e {
-                    return Err(Box::new(e));
+        let store = self.store();
+        let key = self.cache_key();
+        let cache_io = |source| LoaderError::CacheIo {
+            path: store.location(&key),
+            source,
+        };
+        let compressed = S::compression().compress(bytes).map_err(cache_io)?;
+        let meta = CacheMeta::new(headers, compressed.len() as u64, S::cache_version());
+        store
+            .write(&key, &compressed, S::compression(), &meta)
+            .map_err(cache_io)
+    }
+
+    /// キャッシュの取得時刻, 形式が古い場合は `None`
+    fn cache_timestamp(&self, stored: &Stored) -> Option<SystemTime>
+    where
+        S: Cacheable,
+    {
+        match &stored.meta {
+            Some(meta) if meta.schema_version != S::cache_version() => None,
+            _ => Some(stored.fetched_at()),
+        }
+    }
+
+    /// 期限内のキャッシュが無ければ取得する
+    ///
+    /// 取得に失敗した場合は期限切れのキャッシュを返さずにエラーにする
+    /// (期限切れでも使う場合は [Self::get_with])
+    pub async fn get(&self) -> Result<S::Response, LoaderError>
+    where
+        S: Cacheable + 'static,
+    {
+        self.load_fresh().await.map(Loaded::into_inner)
+    }
+
+    /// 結果をプロセス内で共有する (see: [MEMORY])
+    ///
+    /// 期限内であればキャッシュを読み直さずに同じ値を返す, [Self::get] と同じく期限切れは返さない
+    pub async fn get_shared(&self) -> Result<Arc<S::Response>, LoaderError>
+    where
+        S: Cacheable + 'static,
+        S::Response: Send + Sync + 'static,
+    {
+        if let Some(value) = MEMORY.get(&self.cache_root, &self.schema) {
+            return Ok(value);
+        }
+        let loaded = self.load_fresh().await?;
+        let value = Arc::new(loaded.value);
+        let shared = Arc::clone(&value);
+        MEMORY.insert(&self.cache_root, &self.schema, shared, loaded.fetched_at);
+        Ok(value)
+    }
+
+    /// 期限内のキャッシュ, 無ければ取得したもの
+    async fn load_fresh(&self) -> Result<Loaded<S::Response>, LoaderError>
+    where
+        S: Cacheable + 'static,
+    {
+        match self.load_cache_or_miss() {
+            Some(cached) if cached.is_fresh() => Ok(cached),
+            _ => self.fetch_and_save().await,
+        }
+    }
+
+    pub async fn get_with(&self, policy: FetchPolicy) -> Result<Loaded<S::Response>, LoaderError>
+    where
+        S: Cacheable + Clone + Send + Sync + 'static,
+        S::Response: Send,
+    {
+        let cached = match policy {
+            FetchPolicy::NetworkFirst => None,
+            FetchPolicy::CacheOnly => self.load_cache_any()?,
+            _ => self.load_cache_or_miss(),
+        };
+
+        match (policy, cached) {
+            (_, Some(cached)) if cached.is_fresh() => Ok(cached),
+            (FetchPolicy::CacheOnly, Some(stale)) => Ok(stale),
+            (FetchPolicy::CacheOnly, None) => Err(LoaderError::CacheIo {
+                path: self.store().location(&self.cache_key()),
+                source: std::io::ErrorKind::NotFound.into(),
+            }),
+            (FetchPolicy::StaleWhileRevalidate, Some(stale)) => {
+                self.revalidate_in_background();
+                Ok(stale)
+            }
+            (FetchPolicy::NetworkFirst, _) => match self.fetch_and_save().await {
+                Ok(loaded) => Ok(loaded),
+                Err(e) => self.load_cache_or_miss().ok_or(e),
+            },
+            (_, stale) => self.fetch_and_save().await.or_else(|e| stale.ok_or(e)),
+        }
+    }
+
+    /// 応答の要素を1件ずつ読む, 全体をメモリに持たない
+    ///
+    /// 期限内のキャッシュがあればそれを, 無ければサーバーの応答を受信しながら読む
+    /// (サーバーから読んだ場合もキャッシュは更新しない)
+    pub async fn stream(&self) -> Result<Items<S::Item>, LoaderError>
+    where
+        S: Cacheable + Streamable,
+    {
+        if let Some(cached) = self.stream_cache()? {
+            return Ok(cached);
+        }
+        self.validate()?;
+        let endpoint = self.client.endpoint(&self.schema);
+        let decode = move |(json_path, source)| LoaderError::Decode {
+            endpoint: endpoint.clone(),
+            json_path,
+            source,
+        };
+        let chunks = self.client.fetch_stream(&self.schema).await?;
+        let items = (chunks.map(Some))
+            .chain(futures::stream::once(async { None }))
+            .scan(Elements::new(), move |elements, chunk| {
+                let items: Vec<_> = match chunk {
+                    Some(Ok(chunk)) => (elements.feed(&chunk).into_iter())
+                        .map(|item| item.map_err(&decode))
+                        .collect(),
+                    Some(Err(e)) => vec![Err(e.into())],
+                    None => (elements.finish().err())
+                        .map(|e| Err(decode(e)))
+                        .into_iter()
+                        .collect(),
+                };
+                futures::future::ready(Some(futures::stream::iter(items)))
+            })
+            .flatten();
+        Ok(items.boxed())
+    }
+
+    /// 期限内のキャッシュを1件ずつ読む, 無ければ `None`
+    fn stream_cache(&self) -> Result<Option<Items<S::Item>>, LoaderError>
+    where
+        S: Cacheable + Streamable,
+    {
+        let store = self.store();
+        let key = self.cache_key();
+        let path = store.location(&key);
+        let stored = match store.reader(&key) {
+            Ok(Some(stored)) => stored,
+            Ok(None) => return Ok(None),
+            Err(source) if compression::is_corrupt(&source) => {
+                let source = serde_json::Error::io(source);
+                quarantine(&self.cache_root, &*store, &key, path, ".", &source)?;
+                return Ok(None);
+            }
+            Err(source) => return Err(LoaderError::CacheIo { path, source }),
+        };
+        let outdated =
+            (stored.meta.as_ref()).is_some_and(|meta| meta.schema_version != S::cache_version());
+        let fetched_at = stored.fetched_at();
+        let fresh = self.schema.is_finalized_since(fetched_at)
+            || (fetched_at.elapsed()).is_ok_and(|age| age < S::min_interval());
+        if outdated || !fresh {
+            return Ok(None);
+        }
+        println!("Load cache: {:?}", path);
+        // 壊れていた場合は次回取り直すよう隔離する (読み終えた要素は返してしまっている)
+        let cache_root = self.cache_root.clone();
+        let mut quarantined = false;
+        let items = ReadElements::new(stored.reader).map(move |item| {
+            let (json_path, source) = match item {
+                Ok(item) => return Ok(item),
+                Err(ReadError::Io(source)) if compression::is_corrupt(&source) => {
+                    (".".to_string(), serde_json::Error::io(source))
                 }
+                Err(ReadError::Io(source)) => {
+                    let path = path.clone();
+                    return Err(LoaderError::CacheIo { path, source });
+                }
+                Err(ReadError::Element(e)) => e,
+            };
+            if !std::mem::replace(&mut quarantined, true) {
+                let path = path.clone();
+                let isolated = quarantine(&cache_root, &*store, &key, path, &json_path, &source);
+                if let Err(e) = isolated {
+                    eprintln!("{e}");
+                }
+            }
+            Err(LoaderError::CacheCorrupt {
+                path: path.clone(),
+                json_path,
+                source,
+            })
+        });
+        Ok(Some(futures::stream::iter(items).boxed()))
+    }
+
+    /// 取得できないURLの応答 (404のHTMLなど) を保存しないよう送る前に弾く
+    fn validate(&self) -> Result<(), LoaderError> {
+        self.schema
+            .validate()
+            .map_err(|e| LoaderError::InvalidParameters {
+                endpoint: self.client.endpoint(&self.schema),
+                reason: e.to_string(),
+            })
+    }
+
+    /// 期限切れでもキャッシュを読み込む, 無ければ `None`
+    ///
+    /// 壊れていた場合は [Quarantine] に移して `None`
+    fn load_cache_any(&self) -> Result<Option<Loaded<S::Response>>, LoaderError>
+    where
+        S: Cacheable,
+    {
+        match self.read_cache() {
+            Err(LoaderError::CacheCorrupt {
+                path,
+                json_path,
+                source,
+            }) => {
+                let store = self.store();
+                let key = self.cache_key();
+                quarantine(&self.cache_root, &*store, &key, path, &json_path, &source)?;
+                Ok(None)
             }
+            result => result,
         }
+    }
 
-        let api_call = self.call_api().await?.bytes().await?;
-        Ok(serde_json::from_slice(&self.save_cache(&api_call)?)?)
+    /// [Self::load_cache_any] で読めなかった場合は (表示して) キャッシュが無いものとする
+    fn load_cache_or_miss(&self) -> Option<Loaded<S::Response>>
+    where
+        S: Cacheable,
+    {
+        self.load_cache_any().unwrap_or_else(|e| {
+            eprintln!("cache unavailable: {e}");
+            None
+        })
+    }
+
+    fn read_cache(&self) -> Result<Option<Loaded<S::Response>>, LoaderError>
+    where
+        S: Cacheable,
+    {
+        let store = self.store();
+        let key = self.cache_key();
+        let path = store.location(&key);
+        let stored = match store.read(&key) {
+            Ok(Some(stored)) => stored,
+            Ok(None) => return Ok(None),
+            Err(source) if compression::is_corrupt(&source) => return Err(corrupt(path, source)),
+            Err(source) => return Err(LoaderError::CacheIo { path, source }),
+        };
+        println!("Load cache: {:?}", path);
+        let Some(fetched_at) = self.cache_timestamp(&stored) else {
+            return Ok(None);
+        };
+
+        let value = S::format()
+            .decode(&stored.bytes)
+            .map_err(|(json_path, source)| LoaderError::CacheCorrupt {
+                path,
+                json_path,
+                source,
+            })?;
+
+        let age = fetched_at.elapsed().unwrap_or_default();
+        let freshness = if self.schema.is_finalized_since(fetched_at) || age < S::min_interval() {
+            Freshness::Fresh
+        } else {
+            Freshness::Stale { fetched_at, age }
+        };
+        Ok(Some(Loaded {
+            value,
+            fetched_at,
+            freshness,
+        }))
     }
+
+    /// キャッシュにメタデータがあれば条件付きリクエストで再検証する
+    ///
+    /// 同じキャッシュへの取得は (プロセスを跨いでも) 同時に1つだけ行う
+    async fn fetch_and_save(&self) -> Result<Loaded<S::Response>, LoaderError>
+    where
+        S: Cacheable + 'static,
+    {
+        self.validate()?;
+        let store = self.store();
+        let key = self.cache_key();
+        let cache_io = |source| LoaderError::CacheIo {
+            path: store.location(&key),
+            source,
+        };
+        let _lock = match CacheLock::acquire(&store.lock_path(&key)).await {
+            Ok(lock) if lock.waited() => match self.load_cache_any() {
+                // 待っている間に他の取得が終わっていればそれを使う
+                Ok(Some(cached)) if cached.is_fresh() => return Ok(cached),
+                _ => lock,
+            },
+            Ok(lock) => lock,
+            Err(source) => return Err(cache_io(source)),
+        };
+        let meta = (store.read_meta(&key)).filter(|meta| meta.schema_version == S::cache_version());
+        let validators = meta.as_ref().map(CacheMeta::validators).unwrap_or_default();
+
+        let fetched = match self
+            .client
+            .fetch_conditional(&self.schema, &validators)
+            .await?
+        {
+            Fetched::NotModified { headers } => {
+                if let (Some(meta), Ok(Some(cached))) = (meta, self.load_cache_any()) {
+                    println!("Not modified: {:?}", store.location(&key));
+                    store
+                        .write_meta(&key, &meta.revalidated(headers))
+                        .map_err(cache_io)?;
+                    return Ok(Loaded::fresh(cached.value));
+                }
+                // 手元のキャッシュが使えないので取り直す
+                let validators = Validators::default();
+                self.client
+                    .fetch_conditional(&self.schema, &validators)
+                    .await?
+            }
+            fetched => fetched,
+        };
+        let Fetched::Modified { headers, body } = fetched else {
+            unreachable!("304 without validators");
+        };
+
+        // 壊れたデータをキャッシュに残さないよう, 読めることを確かめてから保存する
+        let response = S::format().decode(&body).map_err(|(json_path, source)| {
+            let endpoint = self.client.endpoint(&self.schema);
+            let quarantine = Quarantine::new(&self.cache_root);
+            let _ = quarantine.keep_response(&endpoint, &body, &json_path, &source.to_string());
+            LoaderError::Decode {
+                endpoint,
+                json_path,
+                source,
+            }
+        })?;
+        self.write_cache(&body, headers)?;
+        MEMORY.remove(&self.cache_root, &self.schema);
+        self.archive_snapshot(&body);
+        Ok(Loaded::fresh(response))
+    }
+
+    // スナップショットの保存に失敗しても取得自体は成功とする
+    fn archive_snapshot(&self, bytes: &[u8])
+    where
+        S: Cacheable,
+    {
+        let Some(archive) = (self.archive).and_then(|r| Archive::<S>::new(&self.cache_root, r))
+        else {
+            return;
+        };
+        let result = archive
+            .save(chrono::Local::now().naive_local(), bytes)
+            .and_then(|_| archive.prune());
+        if let Err(e) = result {
+            eprintln!("archive failed: {:?} ({e})", archive.dir());
+        }
+    }
+
+    fn revalidate_in_background(&self)
+    where
+        S: Cacheable + Clone + Send + Sync + 'static,
+        S::Response: Send,
+    {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            eprintln!("no async runtime, skip revalidation");
+            return;
+        };
+        let loader = self.clone();
+        runtime.spawn(async move {
+            if let Err(e) = loader.fetch_and_save().await {
+                eprintln!("revalidation failed: {e}");
+            }
+        });
+    }
+}
+
+/// キャッシュの取り扱い方
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FetchPolicy {
+    /// 常に通信し, 失敗した場合のみ期限切れのキャッシュを返す
+    NetworkFirst,
+    /// 有効なキャッシュが無ければ通信し, 失敗した場合は期限切れのキャッシュを返す
+    #[default]
+    CacheFirst,
+    /// 通信せず, 期限切れでもキャッシュを返す
+    CacheOnly,
+    /// 期限切れのキャッシュを即座に返し, 裏で通信して更新する
+    StaleWhileRevalidate,
+}
+
+/// 読み込み結果と, それが期限切れかどうか
+#[derive(Debug, Clone)]
+pub struct Loaded<T> {
+    pub value: T,
+    /// サーバーから取得 (または再検証) した時刻
+    pub fetched_at: SystemTime,
+    pub freshness: Freshness,
 }
 
-fn get_timestamp(file: &File) -> Result<SystemTime, std::io::Error> {
-    file.metadata()?.modified()
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Freshness {
+    Fresh,
+    /// 期限切れ
+    Stale {
+        /// キャッシュを取得した時刻
+        fetched_at: SystemTime,
+        /// 取得からの経過時間
+        age: Duration,
+    },
+}
+
+impl<T> Loaded<T> {
+    fn fresh(value: T) -> Self {
+        Self {
+            value,
+            fetched_at: SystemTime::now(),
+            freshness: Freshness::Fresh,
+        }
+    }
+
+    pub fn is_fresh(&self) -> bool {
+        self.freshness == Freshness::Fresh
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+/// 展開できないキャッシュ
+fn corrupt(path: PathBuf, source: std::io::Error) -> LoaderError {
+    LoaderError::CacheCorrupt {
+        path,
+        json_path: ".".to_string(),
+        source: serde_json::Error::io(source),
+    }
+}
+
+/// 壊れたキャッシュを [Quarantine] に移して消す
+fn quarantine(
+    cache_root: &Path,
+    store: &dyn CacheStore,
+    key: &Path,
+    path: PathBuf,
+    json_path: &str,
+    source: &serde_json::Error,
+) -> Result<(), LoaderError> {
+    // 展開できない場合は元のファイルのまま残す
+    let bytes = match store.read(key) {
+        Ok(Some(stored)) => stored.bytes,
+        _ => std::fs::read(&path).unwrap_or_default(),
+    };
+    Quarantine::new(cache_root)
+        .isolate_cache(&path, &bytes, json_path, &source.to_string())
+        .and_then(|_| store.remove(key))
+        .map_err(|source| LoaderError::CacheIo { path, source })
 }
 
 pub mod error {
     pub use super::*;
 
+    use std::error::Error;
     use std::fmt::{Display, Formatter, Result};
+    use std::path::Path;
     use std::time::{Duration, SystemTime};
 
+    use reqwest::StatusCode;
+    use url::Url;
+
+    use crate::app::api_client::error::TransportError;
+
     #[derive(Debug)]
     pub enum CacheLoadError {
-        FileNotFound(Box<dyn std::error::Error>),
-        ParseFailed(Box<dyn std::error::Error>),
+        FileNotFound(std::io::Error),
+        ParseFailed {
+            json_path: String,
+            source: serde_json::Error,
+        },
         CacheExpired {
             path: PathBuf,
             updated: SystemTime,
@@ -128,7 +646,9 @@ pub mod error {
         fn fmt(&self, f: &mut Formatter<'_>) -> Result {
             match self {
                 CacheLoadError::FileNotFound(e) => write!(f, "File not found: {}", e),
-                CacheLoadError::ParseFailed(e) => write!(f, "Parse failed: {}", e),
+                CacheLoadError::ParseFailed { json_path, source } => {
+                    write!(f, "Parse failed: {} at {}", source, json_path)
+                }
                 CacheLoadError::CacheExpired {
                     path,
                     updated,
@@ -147,10 +667,129 @@ pub mod error {
     impl Error for CacheLoadError {
         fn source(&self) -> Option<&(dyn Error + 'static)> {
             match self {
-                CacheLoadError::FileNotFound(e) => Some(&**e),
-                CacheLoadError::ParseFailed(e) => Some(&**e),
+                CacheLoadError::FileNotFound(e) => Some(e),
+                CacheLoadError::ParseFailed { source, .. } => Some(source),
                 CacheLoadError::CacheExpired { .. } => None,
             }
         }
     }
+
+    /// [APILoader] の失敗
+    #[derive(Debug)]
+    pub enum LoaderError {
+        /// 通信の失敗
+        Network {
+            endpoint: Url,
+            source: TransportError,
+        },
+        /// 2xx 以外の応答
+        HttpStatus { endpoint: Url, status: StatusCode },
+        /// 応答が [Schema::Response] として読めない
+        Decode {
+            endpoint: Url,
+            json_path: String,
+            source: serde_json::Error,
+        },
+        /// キャッシュファイルの読み書きの失敗
+        CacheIo {
+            path: PathBuf,
+            source: std::io::Error,
+        },
+        /// キャッシュファイルの内容が [Schema::Response] として読めない
+        CacheCorrupt {
+            path: PathBuf,
+            json_path: String,
+            source: serde_json::Error,
+        },
+        /// スキーマのパラメータが不正
+        InvalidParameters { endpoint: Url, reason: String },
+    }
+
+    // タスク間で受け渡せること
+    const _: fn() = || {
+        fn assert_send_sync<T: Send + Sync + 'static>() {}
+        assert_send_sync::<LoaderError>();
+    };
+
+    impl LoaderError {
+        pub fn endpoint(&self) -> Option<&Url> {
+            match self {
+                LoaderError::Network { endpoint, .. }
+                | LoaderError::HttpStatus { endpoint, .. }
+                | LoaderError::Decode { endpoint, .. }
+                | LoaderError::InvalidParameters { endpoint, .. } => Some(endpoint),
+                LoaderError::CacheIo { .. } | LoaderError::CacheCorrupt { .. } => None,
+            }
+        }
+
+        pub fn cache_path(&self) -> Option<&Path> {
+            match self {
+                LoaderError::CacheIo { path, .. } | LoaderError::CacheCorrupt { path, .. } => {
+                    Some(path)
+                }
+                _ => None,
+            }
+        }
+    }
+
+    impl From<FetchError> for LoaderError {
+        fn from(e: FetchError) -> Self {
+            match e {
+                FetchError::Transport { url, source } => LoaderError::Network {
+                    endpoint: url,
+                    source,
+                },
+                FetchError::Status { url, status } => LoaderError::HttpStatus {
+                    endpoint: url,
+                    status,
+                },
+            }
+        }
+    }
+
+    impl Display for LoaderError {
+        fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+            match self {
+                LoaderError::Network { endpoint, source } => {
+                    write!(f, "Network error: {} ({})", endpoint, source)
+                }
+                LoaderError::HttpStatus { endpoint, status } => {
+                    write!(f, "Unexpected status: {} ({})", endpoint, status)
+                }
+                LoaderError::Decode {
+                    endpoint,
+                    json_path,
+                    source,
+                } => write!(
+                    f,
+                    "Decode failed: {} at {} ({})",
+                    endpoint, json_path, source
+                ),
+                LoaderError::CacheIo { path, source } => {
+                    write!(f, "Cache io error: {:?} ({})", path, source)
+                }
+                LoaderError::CacheCorrupt {
+                    path,
+                    json_path,
+                    source,
+                } => write!(f, "Cache corrupt: {:?} at {} ({})", path, json_path, source),
+                LoaderError::InvalidParameters { endpoint, reason } => {
+                    write!(f, "Invalid parameters: {} ({})", endpoint, reason)
+                }
+            }
+        }
+    }
+
+    impl Error for LoaderError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            match self {
+                LoaderError::Network { source, .. } => Some(source),
+                LoaderError::Decode { source, .. } | LoaderError::CacheCorrupt { source, .. } => {
+                    Some(source)
+                }
+                LoaderError::CacheIo { source, .. } => Some(source),
+                LoaderError::HttpStatus { .. } | LoaderError::InvalidParameters { .. } => None,
+            }
+        }
+    }
 }
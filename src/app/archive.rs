@@ -0,0 +1,161 @@
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{Local, NaiveDateTime};
+
+use crate::app::api_loader::error::LoaderError;
+use crate::app::cache::file::write_atomic;
+use crate::app::cache::{Cacheable, compression};
+
+const SNAPSHOT_FORMAT: &str = "%Y-%m-%d_%H%M%S";
+
+/// スナップショットの保持方針, 両方 `None` なら全て残す
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// これより古いものを削除
+    pub max_age: Option<Duration>,
+    /// 新しい方からこの件数を残す
+    pub max_count: Option<usize>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_age: Some(Duration::from_secs(30 * 24 * 3600)),
+            max_count: None,
+        }
+    }
+}
+
+/// 保存済みのスナップショット
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// 取得時刻 (ローカル時刻)
+    pub taken_at: NaiveDateTime,
+    pub path: PathBuf,
+}
+
+/// [Cacheable::archive_dir] を持つスキーマの取得履歴
+///
+/// `<cache root>/<archive dir>/<yyyy-mm-dd>/<yyyy-mm-dd_HHMMSS>.json`
+#[derive(Debug, Clone)]
+pub struct Archive<S> {
+    dir: PathBuf,
+    retention: RetentionPolicy,
+    _schema: PhantomData<fn() -> S>,
+}
+
+impl<S: Cacheable> Archive<S> {
+    /// [Cacheable::archive_dir] が無いスキーマは `None`
+    pub fn new(cache_root: &Path, retention: RetentionPolicy) -> Option<Self> {
+        Some(Self {
+            dir: cache_root.join(S::archive_dir()?),
+            retention,
+            _schema: PhantomData,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn save(&self, taken_at: NaiveDateTime, bytes: &[u8]) -> std::io::Result<Snapshot> {
+        let day_dir = self.dir.join(taken_at.format("%Y-%m-%d").to_string());
+        std::fs::create_dir_all(&day_dir)?;
+        let path = day_dir.join(format!("{}.json", taken_at.format(SNAPSHOT_FORMAT)));
+        let path = S::compression().apply(&path);
+        write_atomic(&path, &S::compression().compress(bytes)?)?;
+        println!("Save snapshot: {:?}", path);
+        Ok(Snapshot { taken_at, path })
+    }
+
+    /// 古い順
+    pub fn list(&self) -> std::io::Result<Vec<Snapshot>> {
+        let mut snapshots = Vec::new();
+        let days = match std::fs::read_dir(&self.dir) {
+            Ok(days) => days,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(snapshots),
+            Err(e) => return Err(e),
+        };
+        for day in days {
+            let day = day?;
+            if !day.file_type()?.is_dir() {
+                continue;
+            }
+            for entry in std::fs::read_dir(day.path())? {
+                let path = entry?.path();
+                let taken_at = (path.file_name().and_then(|s| s.to_str()))
+                    .and_then(|s| compression::strip_suffix(s).0.strip_suffix(".json"))
+                    .and_then(|s| NaiveDateTime::parse_from_str(s, SNAPSHOT_FORMAT).ok());
+                if let Some(taken_at) = taken_at {
+                    snapshots.push(Snapshot { taken_at, path });
+                }
+            }
+        }
+        snapshots.sort_by_key(|s| s.taken_at);
+        Ok(snapshots)
+    }
+
+    pub fn load(&self, snapshot: &Snapshot) -> Result<S::Response, LoaderError> {
+        let bytes = std::fs::read(&snapshot.path)
+            .and_then(compression::decompress)
+            .map_err(|source| LoaderError::CacheIo {
+                path: snapshot.path.clone(),
+                source,
+            })?;
+        S::format()
+            .decode(&bytes)
+            .map_err(|(json_path, source)| LoaderError::CacheCorrupt {
+                path: snapshot.path.clone(),
+                json_path,
+                source,
+            })
+    }
+
+    /// `at` に最も近い時刻のスナップショット
+    pub fn nearest(&self, at: NaiveDateTime) -> std::io::Result<Option<Snapshot>> {
+        Ok((self.list()?.into_iter()).min_by_key(|s| (s.taken_at - at).abs()))
+    }
+
+    pub fn load_nearest(
+        &self,
+        at: NaiveDateTime,
+    ) -> Result<Option<(Snapshot, S::Response)>, LoaderError> {
+        let snapshot = self.nearest(at).map_err(|source| LoaderError::CacheIo {
+            path: self.dir.clone(),
+            source,
+        })?;
+        snapshot
+            .map(|snapshot| self.load(&snapshot).map(|response| (snapshot, response)))
+            .transpose()
+    }
+
+    /// [RetentionPolicy] に従って削除し, 削除したものを返す
+    pub fn prune(&self) -> std::io::Result<Vec<Snapshot>> {
+        let snapshots = self.list()?;
+        let now = Local::now().naive_local();
+
+        let keep_from = self
+            .retention
+            .max_count
+            .map_or(0, |count| snapshots.len().saturating_sub(count));
+        let expired = |s: &Snapshot| {
+            let max_age = self.retention.max_age;
+            max_age.is_some_and(|max_age| (now - s.taken_at).to_std().is_ok_and(|t| t > max_age))
+        };
+
+        let mut removed = Vec::new();
+        for (i, snapshot) in snapshots.into_iter().enumerate() {
+            if i < keep_from || expired(&snapshot) {
+                std::fs::remove_file(&snapshot.path)?;
+                if let Some(day_dir) = snapshot.path.parent() {
+                    // 空になった日付ディレクトリも消す
+                    let _ = std::fs::remove_dir(day_dir);
+                }
+                removed.push(snapshot);
+            }
+        }
+        Ok(removed)
+    }
+}
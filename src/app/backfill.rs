@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use chrono::{Datelike, Months, NaiveDate, NaiveTime, TimeDelta, Utc};
+use futures::StreamExt;
+use tokio::time::Instant;
+
+use crate::api::model::ranking::EPOCH;
+use crate::api::schema::error::InvalidParameter;
+use crate::api::schema::server_time;
+use crate::app::api_client::ApiClient;
+use crate::app::api_loader::error::LoaderError;
+use crate::app::api_loader::{APILoader, FetchPolicy};
+use crate::app::cache::store::{self, CacheStore};
+use crate::app::cache::{Cacheable, DEFAULT_CACHE_ROOT};
+
+/// 両端を含む日付の範囲
+///
+/// 開始は [EPOCH] より前に, 終了は今日 (サーバーの日付) より後にならないよう切り詰める
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateRange {
+    start: NaiveDate,
+    end: NaiveDate,
+}
+
+impl DateRange {
+    pub fn new(start: NaiveDate, end: NaiveDate) -> Self {
+        Self {
+            start: start.max(EPOCH),
+            end: end.min(server_time(Utc::now()).date()),
+        }
+    }
+
+    /// [EPOCH] から今日まで
+    pub fn all() -> Self {
+        Self::new(EPOCH, NaiveDate::MAX)
+    }
+
+    pub fn start(&self) -> NaiveDate {
+        self.start
+    }
+
+    pub fn end(&self) -> NaiveDate {
+        self.end
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.end < self.start
+    }
+
+    pub fn days(&self) -> impl Iterator<Item = NaiveDate> + use<> {
+        let end = self.end;
+        self.start.iter_days().take_while(move |date| *date <= end)
+    }
+
+    /// 各月の1日
+    pub fn months(&self) -> impl Iterator<Item = NaiveDate> + use<> {
+        let end = self.end;
+        let first = self.start.with_day(1).filter(|_| !self.is_empty());
+        std::iter::successors(first, |ym| ym.checked_add_months(Months::new(1)))
+            .take_while(move |ym| *ym <= end)
+    }
+
+    /// 終了した時間帯のみ (サーバーの時刻)
+    pub fn hours(&self) -> impl Iterator<Item = (NaiveDate, u8)> + use<> {
+        let now = server_time(Utc::now());
+        self.days()
+            .flat_map(|date| (0..24).map(move |hour| (date, hour)))
+            .filter(move |(date, hour)| {
+                NaiveTime::from_hms_opt((*hour).into(), 0, 0)
+                    .is_some_and(|time| date.and_time(time) + TimeDelta::hours(1) <= now)
+            })
+    }
+}
+
+/// 取得の設定
+#[derive(Debug, Clone)]
+pub struct BackfillOptions {
+    /// 同時に取得する数
+    pub concurrency: usize,
+    /// リクエストを送る最小間隔 (全体で)
+    pub delay: Duration,
+    pub client: ApiClient,
+    pub cache_root: PathBuf,
+    /// `None` の場合は `cache_root` に設定された保存先
+    pub store: Option<Arc<dyn CacheStore>>,
+}
+
+impl Default for BackfillOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            delay: Duration::from_millis(500),
+            client: ApiClient::default(),
+            cache_root: DEFAULT_CACHE_ROOT.to_path_buf(),
+            store: None,
+        }
+    }
+}
+
+/// 1件毎の結果
+#[derive(Debug)]
+pub enum Outcome {
+    /// 確定済みのキャッシュがあるので取得しなかった
+    Skipped,
+    /// 取得した (または期限内のキャッシュがあった)
+    Fetched,
+    /// 取得に失敗し, 期限切れのキャッシュが残っている
+    Stale,
+    /// まだ公開されていないので取得しなかった
+    Unpublished(InvalidParameter),
+    Failed(LoaderError),
+}
+
+/// 進捗の通知
+#[derive(Debug)]
+pub struct Progress<'a, S> {
+    /// 終わった件数 (この1件を含む)
+    pub done: usize,
+    pub total: usize,
+    pub schema: &'a S,
+    pub outcome: &'a Outcome,
+}
+
+impl<S: std::fmt::Debug> Display for Progress<'_, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}/{}] {:?}: ", self.done, self.total, self.schema)?;
+        match self.outcome {
+            Outcome::Skipped => write!(f, "skipped"),
+            Outcome::Fetched => write!(f, "fetched"),
+            Outcome::Stale => write!(f, "stale"),
+            Outcome::Unpublished(e) => write!(f, "unpublished ({e})"),
+            Outcome::Failed(e) => write!(f, "failed ({e})"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BackfillReport<S> {
+    pub fetched: usize,
+    pub skipped: usize,
+    /// まだ公開されていないもの, 公開後に再実行すれば取得する
+    pub unpublished: usize,
+    /// 失敗したもの (期限切れのキャッシュが残っているものを含む), 再実行で取り直す
+    pub failed: Vec<(S, Option<LoaderError>)>,
+}
+
+impl<S> Default for BackfillReport<S> {
+    fn default() -> Self {
+        Self {
+            fetched: 0,
+            skipped: 0,
+            unpublished: 0,
+            failed: Vec::new(),
+        }
+    }
+}
+
+impl<S> Display for BackfillReport<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "fetched {}, skipped {}, unpublished {}, failed {}",
+            self.fetched,
+            self.skipped,
+            self.unpublished,
+            self.failed.len()
+        )
+    }
+}
+
+/// 日付毎のスキーマをまとめて取得する
+///
+/// 確定済みのキャッシュは取得しないので, 中断しても同じ引数で再実行すれば続きから取得する.
+/// 取得できないパラメータ (未公開など) は送らずに結果にする
+/// (e.g. `backfill(DateRange::new(start, end).days().map(Report), &options, |p| println!("{p}"))`)
+pub async fn backfill<S>(
+    schemas: impl IntoIterator<Item = S>,
+    options: &BackfillOptions,
+    mut on_progress: impl FnMut(Progress<'_, S>),
+) -> BackfillReport<S>
+where
+    S: Cacheable + Clone + Send + Sync + 'static,
+    S::Response: Send,
+{
+    let store = (options.store.clone()).unwrap_or_else(|| store::open(&options.cache_root));
+    let cached_at = cached_at::<S>(&*store);
+    let schemas: Vec<S> = schemas.into_iter().collect();
+    let total = schemas.len();
+    let throttle = Throttle::new(options.delay);
+
+    let mut report = BackfillReport::default();
+    let mut results = futures::stream::iter(schemas)
+        .map(|schema| {
+            let cached_at = &cached_at;
+            let throttle = &throttle;
+            let store = Arc::clone(&store);
+            async move {
+                let fetched_at = cached_at.get(&schema.file_path());
+                if fetched_at.is_some_and(|t| schema.is_finalized_since(*t)) {
+                    return (schema, Outcome::Skipped);
+                }
+                match schema.validate() {
+                    Ok(()) => {}
+                    Err(e @ InvalidParameter::NotPublished(_)) => {
+                        return (schema, Outcome::Unpublished(e));
+                    }
+                    Err(e) => {
+                        let e = LoaderError::InvalidParameters {
+                            endpoint: options.client.endpoint(&schema),
+                            reason: e.to_string(),
+                        };
+                        return (schema, Outcome::Failed(e));
+                    }
+                }
+                throttle.wait().await;
+                let mut loader = APILoader::with_client(schema.clone(), options.client.clone());
+                loader
+                    .set_cache_root(options.cache_root.clone())
+                    .set_store(store);
+                let outcome = match loader.get_with(FetchPolicy::CacheFirst).await {
+                    Ok(loaded) if loaded.is_fresh() => Outcome::Fetched,
+                    Ok(_) => Outcome::Stale,
+                    Err(e) => Outcome::Failed(e),
+                };
+                (schema, outcome)
+            }
+        })
+        .buffer_unordered(options.concurrency.max(1));
+
+    let mut done = 0;
+    while let Some((schema, outcome)) = results.next().await {
+        done += 1;
+        on_progress(Progress {
+            done,
+            total,
+            schema: &schema,
+            outcome: &outcome,
+        });
+        match outcome {
+            Outcome::Skipped => report.skipped += 1,
+            Outcome::Fetched => report.fetched += 1,
+            Outcome::Stale => report.failed.push((schema, None)),
+            Outcome::Unpublished(_) => report.unpublished += 1,
+            Outcome::Failed(e) => report.failed.push((schema, Some(e))),
+        }
+    }
+    report
+}
+
+/// 保存済みのキャッシュの取得時刻 (key: [Cacheable::file_path])
+fn cached_at<S: Cacheable>(store: &dyn CacheStore) -> HashMap<PathBuf, SystemTime> {
+    let dir = S::file_dir().map(|dir| dir.as_ref().to_path_buf());
+    let infos = store
+        .list(dir.as_deref().unwrap_or(Path::new("")))
+        .inspect_err(|e| eprintln!("cannot list cache: {e}"))
+        .unwrap_or_default();
+    infos
+        .into_iter()
+        .map(|info| (info.key, info.fetched_at))
+        .collect()
+}
+
+/// リクエストの開始を全体で `delay` 以上空ける
+#[derive(Debug)]
+struct Throttle {
+    delay: Duration,
+    next: Mutex<Instant>,
+}
+
+impl Throttle {
+    fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    async fn wait(&self) {
+        let start = {
+            let mut next = self.next.lock().unwrap_or_else(|e| e.into_inner());
+            let start = (*next).max(Instant::now());
+            *next = start + self.delay;
+            start
+        };
+        tokio::time::sleep_until(start).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Timelike;
+
+    use super::*;
+
+    fn ymd(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn clamped() {
+        let range = DateRange::new(ymd(2000, 1, 1), NaiveDate::MAX);
+        assert_eq!(range.start(), EPOCH);
+        assert_eq!(range.end(), server_time(Utc::now()).date());
+        assert_eq!(DateRange::all(), range);
+
+        let empty = DateRange::new(ymd(2024, 3, 2), ymd(2024, 3, 1));
+        assert!(empty.is_empty());
+        assert_eq!(empty.days().count(), 0);
+        assert_eq!(empty.months().count(), 0);
+        assert_eq!(empty.hours().count(), 0);
+    }
+
+    #[test]
+    fn days_across_month_end() {
+        let range = DateRange::new(ymd(2024, 2, 28), ymd(2024, 3, 1));
+        let days: Vec<_> = range.days().collect();
+        assert_eq!(days, [ymd(2024, 2, 28), ymd(2024, 2, 29), ymd(2024, 3, 1)]);
+
+        let range = DateRange::new(ymd(2023, 12, 31), ymd(2024, 1, 1));
+        assert_eq!(
+            range.days().collect::<Vec<_>>(),
+            [ymd(2023, 12, 31), ymd(2024, 1, 1)]
+        );
+    }
+
+    #[test]
+    fn months_from_first_days() {
+        let range = DateRange::new(ymd(2023, 12, 31), ymd(2024, 2, 1));
+        let months: Vec<_> = range.months().collect();
+        assert_eq!(months, [ymd(2023, 12, 1), ymd(2024, 1, 1), ymd(2024, 2, 1)]);
+
+        // 月の途中だけでもその月を含む
+        let range = DateRange::new(ymd(2024, 1, 15), ymd(2024, 1, 20));
+        assert_eq!(range.months().collect::<Vec<_>>(), [ymd(2024, 1, 1)]);
+    }
+
+    #[test]
+    fn hours_across_day_end() {
+        let range = DateRange::new(ymd(2023, 12, 31), ymd(2024, 1, 1));
+        let hours: Vec<_> = range.hours().collect();
+        assert_eq!(hours.len(), 48);
+        assert_eq!(hours[0], (ymd(2023, 12, 31), 0));
+        assert_eq!(hours[23], (ymd(2023, 12, 31), 23));
+        assert_eq!(hours[24], (ymd(2024, 1, 1), 0));
+        assert_eq!(hours[47], (ymd(2024, 1, 1), 23));
+    }
+
+    #[test]
+    fn hours_until_now() {
+        let now = server_time(Utc::now());
+        let range = DateRange::new(now.date(), now.date());
+        let hours: Vec<_> = range.hours().collect();
+        // 今の時間帯は終わっていない
+        assert_eq!(hours.len(), now.hour() as usize);
+    }
+}
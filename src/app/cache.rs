@@ -1,12 +1,38 @@
+pub mod compression;
+pub mod file;
+pub mod index;
+pub mod memory;
+pub mod meta;
+pub mod quarantine;
+pub mod store;
+
 use std::{
     path::{Path, PathBuf},
     sync::LazyLock,
+    time::SystemTime,
 };
 
-use crate::api::schema::*;
-use chrono::Datelike;
+use crate::api::{model::shop, schema::*};
+use crate::app::config::{APP_NAME, CONFIG};
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use compression::Compression;
+
+/// キャッシュの保存先を上書きする環境変数
+pub const CACHE_ROOT_ENV: &str = "SO2_TOOL_CACHE_DIR";
 
-pub static DEFAULT_CACHE_ROOT: LazyLock<&Path> = LazyLock::new(|| Path::new(r"data\api\cache"));
+/// 優先順: 環境変数 [CACHE_ROOT_ENV] > 設定ファイル > OS既定のキャッシュディレクトリ
+///
+/// - Linux: `$XDG_CACHE_HOME/so2_tool` (`~/.cache/so2_tool`)
+/// - Windows: `%LOCALAPPDATA%\so2_tool`
+/// - macOS: `~/Library/Caches/so2_tool`
+pub static DEFAULT_CACHE_ROOT: LazyLock<PathBuf> = LazyLock::new(|| {
+    std::env::var_os(CACHE_ROOT_ENV)
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| CONFIG.cache_root.clone())
+        .or_else(|| dirs::cache_dir().map(|dir| dir.join(APP_NAME)))
+        .unwrap_or_else(|| ["data", "api", "cache"].iter().collect())
+});
 
 pub trait Cacheable: Schema {
     // use for delete expired cache
@@ -22,24 +48,85 @@ pub trait Cacheable: Schema {
             |dir| dir.as_ref().join(self.file_name()),
         )
     }
+
+    /// 取得毎のスナップショットを残す場合の保存先 (see: [Archive](crate::app::archive::Archive))
+    fn archive_dir() -> Option<impl AsRef<Path>> {
+        Option::<&Path>::None
+    }
+
+    /// 大きいものは圧縮して保存する
+    fn compression() -> Compression {
+        Compression::None
+    }
+
+    /// キャッシュの形式を変えた場合に上げる, 異なるバージョンのキャッシュは使わない
+    fn cache_version() -> u32 {
+        1
+    }
+
+    /// [file_name](Cacheable::file_name) の逆変換
+    fn from_file_name(_file_name: &str) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    /// サーバー側のデータがこれ以上変化しなくなる時刻 (サーバーの時刻, see: [server_time])
+    ///
+    /// 過去の日付のレポート等が該当し, この時刻以降に取得したキャッシュは期限切れにならない
+    fn finalized_at(&self) -> Option<NaiveDateTime> {
+        None
+    }
+
+    /// `fetched_at` に取得したキャッシュが確定済みのデータか
+    fn is_finalized_since(&self, fetched_at: SystemTime) -> bool {
+        self.finalized_at()
+            .is_some_and(|t| t <= server_time(fetched_at))
+    }
+}
+
+fn parse_date(s: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()
+}
+
+fn parse_month(s: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(&format!("{s}-01"), "%Y-%m-%d").ok()
+}
+
+/// `{prefix}{args}.json` の `args` 部分
+fn file_name_args<'a>(file_name: &'a str, prefix: &str) -> Option<&'a str> {
+    file_name.strip_prefix(prefix)?.strip_suffix(".json")
 }
 
 impl Cacheable for OfficialItem {
     fn file_name(&self) -> impl AsRef<Path> {
         "item.json"
     }
+
+    fn from_file_name(file_name: &str) -> Option<Self> {
+        (file_name == "item.json").then_some(OfficialItem)
+    }
 }
 
 impl Cacheable for RecipeItem {
     fn file_name(&self) -> impl AsRef<Path> {
         "recipe_item.json"
     }
+
+    fn from_file_name(file_name: &str) -> Option<Self> {
+        (file_name == "recipe_item.json").then_some(RecipeItem)
+    }
 }
 
 impl Cacheable for Area {
     fn file_name(&self) -> impl AsRef<Path> {
         "area.json"
     }
+
+    fn from_file_name(file_name: &str) -> Option<Self> {
+        (file_name == "area.json").then_some(Area)
+    }
 }
 
 impl Cacheable for Report {
@@ -53,29 +140,65 @@ impl Cacheable for Report {
         let dd = self.0.day();
         format!("report_{yyyy:04}-{mm:02}-{dd:02}.json")
     }
+
+    fn from_file_name(file_name: &str) -> Option<Self> {
+        parse_date(file_name_args(file_name, "report_")?).map(Report)
+    }
+
+    fn finalized_at(&self) -> Option<NaiveDateTime> {
+        end_of_day(self.0)
+    }
+
+    fn compression() -> Compression {
+        Compression::Gzip
+    }
 }
 
 impl Cacheable for ShopSummary {
     fn file_name(&self) -> impl AsRef<Path> {
         "shop_summary.json"
     }
+
+    fn from_file_name(file_name: &str) -> Option<Self> {
+        (file_name == "shop_summary.json").then_some(ShopSummary)
+    }
 }
 
 impl Cacheable for Shop {
     fn file_name(&self) -> impl AsRef<Path> {
         "shop.json"
     }
+
+    fn from_file_name(file_name: &str) -> Option<Self> {
+        (file_name == "shop.json").then_some(Shop)
+    }
+
+    fn compression() -> Compression {
+        Compression::Gzip
+    }
 }
 
 impl Cacheable for People {
     fn file_name(&self) -> impl AsRef<Path> {
         "people.json"
     }
+
+    fn from_file_name(file_name: &str) -> Option<Self> {
+        (file_name == "people.json").then_some(People)
+    }
+
+    fn archive_dir() -> Option<impl AsRef<Path>> {
+        Some(Path::new("archive").join("people"))
+    }
+
+    fn compression() -> Compression {
+        Compression::Gzip
+    }
 }
 
 impl Cacheable for RankingAllMonthly {
     fn file_dir() -> Option<impl AsRef<Path>> {
-        Some("ranking/monthly_all")
+        Some(Path::new("ranking").join("monthly_all"))
     }
 
     fn file_name(&self) -> impl AsRef<Path> {
@@ -83,11 +206,20 @@ impl Cacheable for RankingAllMonthly {
         let month = self.ym.month();
         format!("ranking_monthly_all_{year:04}-{month:02}.json")
     }
+
+    fn from_file_name(file_name: &str) -> Option<Self> {
+        let ym = parse_month(file_name_args(file_name, "ranking_monthly_all_")?)?;
+        Some(Self { ym })
+    }
+
+    fn finalized_at(&self) -> Option<NaiveDateTime> {
+        end_of_month(self.ym)
+    }
 }
 
 impl Cacheable for RankingSectionMonthly {
     fn file_dir() -> Option<impl AsRef<Path>> {
-        Some("ranking/section_monthly")
+        Some(Path::new("ranking").join("section_monthly"))
     }
 
     fn file_name(&self) -> impl AsRef<Path> {
@@ -96,11 +228,24 @@ impl Cacheable for RankingSectionMonthly {
         let section = &self.section;
         format!("ranking_monthly_{section}_{year:04}-{month:02}.json")
     }
+
+    fn from_file_name(file_name: &str) -> Option<Self> {
+        let args = file_name_args(file_name, "ranking_monthly_")?;
+        let (section, ym) = args.rsplit_once('_')?;
+        Some(Self {
+            ym: parse_month(ym)?,
+            section: section.to_string(),
+        })
+    }
+
+    fn finalized_at(&self) -> Option<NaiveDateTime> {
+        end_of_month(self.ym)
+    }
 }
 
 impl Cacheable for RankingSectionDaily {
     fn file_dir() -> Option<impl AsRef<Path>> {
-        Some("ranking/daily")
+        Some(Path::new("ranking").join("daily"))
     }
 
     fn file_name(&self) -> impl AsRef<Path> {
@@ -110,18 +255,55 @@ impl Cacheable for RankingSectionDaily {
         let section = &self.section;
         format!("ranking_daily_{section}_{year:04}-{month:02}-{day:02}.json")
     }
+
+    fn from_file_name(file_name: &str) -> Option<Self> {
+        let args = file_name_args(file_name, "ranking_daily_")?;
+        let (section, date) = args.rsplit_once('_')?;
+        Some(Self {
+            date: parse_date(date)?,
+            section: section.to_string(),
+        })
+    }
+
+    fn finalized_at(&self) -> Option<NaiveDateTime> {
+        end_of_day(self.date)
+    }
 }
 
 impl Cacheable for Sale {
     fn file_name(&self) -> impl AsRef<Path> {
         "sale.json"
     }
+
+    fn from_file_name(file_name: &str) -> Option<Self> {
+        (file_name == "sale.json").then_some(Sale)
+    }
+
+    fn archive_dir() -> Option<impl AsRef<Path>> {
+        Some(Path::new("archive").join("sale"))
+    }
+
+    fn compression() -> Compression {
+        Compression::Gzip
+    }
 }
 
 impl Cacheable for Request {
     fn file_name(&self) -> impl AsRef<Path> {
         "request.json"
     }
+
+    fn from_file_name(file_name: &str) -> Option<Self> {
+        (file_name == "request.json").then_some(Request)
+    }
+
+    fn archive_dir() -> Option<impl AsRef<Path>> {
+        Some(Path::new("archive").join("request"))
+    }
+
+    fn compression() -> Compression {
+        Compression::Gzip
+    }
 }
 
 impl Cacheable for RequestReport {
@@ -136,10 +318,134 @@ impl Cacheable for RequestReport {
         };
         format!("request_report_{arg}.json")
     }
+
+    fn from_file_name(file_name: &str) -> Option<Self> {
+        let args = file_name_args(file_name, "request_report_")?;
+        let (date, arg) = args.split_once('_')?;
+        let date = parse_date(date)?;
+        if let Some(shop_id) = arg.strip_prefix('#') {
+            let shop_id = shop::Id(shop_id.parse().ok()?);
+            Some(RequestReport::Shop { date, shop_id })
+        } else {
+            let hour = arg.strip_suffix('h')?.parse().ok()?;
+            Some(RequestReport::All { date, hour })
+        }
+    }
+
+    /// 2: 整形せずにサーバーの応答をそのまま保存する
+    fn cache_version() -> u32 {
+        2
+    }
+
+    fn finalized_at(&self) -> Option<NaiveDateTime> {
+        match self {
+            RequestReport::All { date, hour } => end_of_hour(*date, *hour),
+            RequestReport::Shop { date, .. } => end_of_day(*date),
+        }
+    }
 }
 
 impl Cacheable for AreaSummary {
     fn file_name(&self) -> impl AsRef<Path> {
         "area_summary.json"
     }
+
+    fn from_file_name(file_name: &str) -> Option<Self> {
+        (file_name == "area_summary.json").then_some(AreaSummary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveTime, TimeZone, Utc};
+
+    use super::*;
+
+    fn utc(date: NaiveDate, h: u32, m: u32) -> SystemTime {
+        let time = NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        Utc.from_utc_datetime(&date.and_time(time)).into()
+    }
+
+    #[test]
+    fn finalized_in_server_time() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+        let next = date.succ_opt().unwrap();
+
+        // 翌日1時 (日本時間) = 当日16時 (UTC)
+        let report = Report(date);
+        assert!(!report.is_finalized_since(utc(date, 15, 59)));
+        assert!(report.is_finalized_since(utc(date, 16, 0)));
+
+        // 5時台は7時 (日本時間) = 前日22時 (UTC) に確定する
+        let hour = RequestReport::All {
+            date: next,
+            hour: 5,
+        };
+        assert!(!hour.is_finalized_since(utc(date, 21, 59)));
+        assert!(hour.is_finalized_since(utc(date, 22, 0)));
+
+        // 翌月1日1時 (日本時間) = 月末16時 (UTC)
+        let month = RankingAllMonthly { ym: date };
+        let last = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+        assert!(!month.is_finalized_since(utc(last, 15, 59)));
+        assert!(month.is_finalized_since(utc(last, 16, 0)));
+    }
+
+    /// `from_file_name(file_name(x)) == x` (スキーマは `PartialEq` でないので `Debug` で比べる)
+    fn round_trip<S: Cacheable + std::fmt::Debug>(schema: S) {
+        let file_name = schema.file_name();
+        let file_name = file_name.as_ref().to_str().unwrap();
+        let parsed = S::from_file_name(file_name);
+        assert_eq!(
+            format!("{parsed:?}"),
+            format!("{:?}", Some(&schema)),
+            "{file_name}"
+        );
+    }
+
+    #[test]
+    fn file_name_round_trip() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+        let ym = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+
+        round_trip(OfficialItem);
+        round_trip(RecipeItem);
+        round_trip(Area);
+        round_trip(Report(date));
+        round_trip(RankingAllMonthly { ym });
+        round_trip(RankingSectionMonthly {
+            ym,
+            section: "exp_62".to_string(),
+        });
+        round_trip(RankingSectionDaily {
+            date,
+            section: "exp_62".to_string(),
+        });
+        round_trip(Sale);
+        round_trip(Request);
+        round_trip(ShopSummary);
+        round_trip(Shop);
+        round_trip(People);
+        round_trip(RequestReport::All { date, hour: 5 });
+        round_trip(RequestReport::Shop {
+            date,
+            shop_id: shop::Id(123),
+        });
+        round_trip(AreaSummary);
+    }
+
+    #[test]
+    fn unrelated_file_names() {
+        assert!(Sale::from_file_name("request.json").is_none());
+        assert!(Sale::from_file_name("sale.json.meta").is_none());
+        assert!(Report::from_file_name("report_2024-03-10.txt").is_none());
+        assert!(Report::from_file_name("report_latest.json").is_none());
+        assert!(RankingAllMonthly::from_file_name("ranking_monthly_exp_62_2024-03.json").is_none());
+        assert!(RankingSectionMonthly::from_file_name("ranking_monthly_2024-03.json").is_none());
+        assert!(RankingSectionDaily::from_file_name("ranking_daily_2024-03-10.json").is_none());
+        assert!(RequestReport::from_file_name("request_report_2024-03-10.json").is_none());
+        assert!(RequestReport::from_file_name("request_report_2024-03-10_5.json").is_none());
+        assert!(RequestReport::from_file_name("request_report_2024-03-10_#x.json").is_none());
+        assert!(AreaSummary::from_file_name("area.json").is_none());
+    }
 }
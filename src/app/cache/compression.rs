@@ -0,0 +1,83 @@
+use std::borrow::Cow;
+use std::io::{BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+
+use crate::app::cache::file::with_suffix;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// キャッシュファイルの圧縮形式
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    /// `<file name>.gz`
+    Gzip,
+}
+
+impl Compression {
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            Compression::None => "",
+            Compression::Gzip => ".gz",
+        }
+    }
+
+    pub fn apply(&self, path: &Path) -> PathBuf {
+        with_suffix(path, self.suffix())
+    }
+
+    pub fn compress<'a>(&self, bytes: &'a [u8]) -> std::io::Result<Cow<'a, [u8]>> {
+        match self {
+            Compression::None => Ok(Cow::Borrowed(bytes)),
+            Compression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(bytes)?;
+                Ok(Cow::Owned(encoder.finish()?))
+            }
+        }
+    }
+}
+
+/// 先頭のマジックナンバーで判別して展開する (非圧縮のJSONはそのまま)
+pub fn decompress(bytes: Vec<u8>) -> std::io::Result<Vec<u8>> {
+    if bytes.starts_with(&GZIP_MAGIC) {
+        let mut decompressed = Vec::with_capacity(bytes.len() * 8);
+        GzDecoder::new(&bytes[..]).read_to_end(&mut decompressed)?;
+        Ok(decompressed)
+    } else {
+        Ok(bytes)
+    }
+}
+
+/// [decompress] の [Read] 版, 全体を展開せずに読む
+pub fn decompress_reader<R>(mut reader: R) -> std::io::Result<Box<dyn Read + Send>>
+where
+    R: BufRead + Send + 'static,
+{
+    if reader.fill_buf()?.starts_with(&GZIP_MAGIC) {
+        Ok(Box::new(GzDecoder::new(reader)))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// 展開の失敗 (圧縮データが壊れている, 途中で切れている) か
+///
+/// [decompress] や [decompress_reader] から読んだ際のエラーを判別する
+/// (壊れたdeflateは `InvalidInput`, 途中で切れたものは `UnexpectedEof` になる)
+pub fn is_corrupt(e: &std::io::Error) -> bool {
+    use std::io::ErrorKind::*;
+    matches!(e.kind(), InvalidData | InvalidInput | UnexpectedEof)
+}
+
+/// `file_name` から圧縮形式の拡張子を除く
+pub fn strip_suffix(file_name: &str) -> (&str, Compression) {
+    match file_name.strip_suffix(Compression::Gzip.suffix()) {
+        Some(stripped) => (stripped, Compression::Gzip),
+        None => (file_name, Compression::None),
+    }
+}
@@ -0,0 +1,81 @@
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use fs4::fs_std::FileExt;
+
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+pub(crate) fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = OsString::from(path);
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// 一時ファイルに書き出してから置き換える
+///
+/// 途中で中断されたり同時に書き込まれても, 読み手が中途半端な内容を見ることはない
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let suffix = format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    let tmp = with_suffix(path, &suffix);
+
+    let result = File::create(&tmp)
+        .and_then(|mut file| {
+            file.write_all(bytes)?;
+            file.sync_all()
+        })
+        .and_then(|_| std::fs::rename(&tmp, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// キャッシュ毎の排他ロック (see: [CacheStore::lock_path](super::store::CacheStore::lock_path))
+///
+/// OSのアドバイザリロックなのでプロセス間でも有効, drop で解放される
+#[derive(Debug)]
+pub struct CacheLock {
+    file: File,
+    waited: bool,
+}
+
+impl CacheLock {
+    pub async fn acquire(path: &Path) -> std::io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)?;
+
+        let mut waited = false;
+        while !file.try_lock_exclusive()? {
+            waited = true;
+            tokio::time::sleep(LOCK_POLL_INTERVAL).await;
+        }
+        Ok(Self { file, waited })
+    }
+
+    /// 他の誰かがロックを持っていて待たされたか
+    pub fn waited(&self) -> bool {
+        self.waited
+    }
+}
+
+impl Drop for CacheLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.file);
+    }
+}
@@ -0,0 +1,163 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::api::schema::*;
+use crate::app::cache::Cacheable;
+use crate::app::cache::compression::Compression;
+use crate::app::cache::store::CacheStore;
+
+macro_rules! cached_schema {
+    ( $( $name:ident ),+ $(,)? ) => {
+        /// キャッシュファイルから復元したスキーマとパラメータ
+        #[derive(Debug, Clone)]
+        pub enum CachedSchema {
+            $( $name($name), )+
+        }
+
+        impl CachedSchema {
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $( CachedSchema::$name(_) => stringify!($name), )+
+                }
+            }
+
+            /// [Cacheable::file_dir]
+            pub fn file_dir(&self) -> Option<PathBuf> {
+                match self {
+                    $( CachedSchema::$name(_) => $name::file_dir().map(|d| d.as_ref().to_path_buf()), )+
+                }
+            }
+        }
+
+        impl Display for CachedSchema {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    $( CachedSchema::$name(schema) => write!(f, "{}: {:?}", stringify!($name), schema), )+
+                }
+            }
+        }
+
+        impl CacheIndex {
+            /// `store` の全てのキャッシュを列挙する
+            pub fn scan(store: &dyn CacheStore) -> std::io::Result<Self> {
+                let mut entries = Vec::new();
+                $( scan::<$name>(store, CachedSchema::$name, &mut entries)?; )+
+                entries.sort_by(|a, b| a.path.cmp(&b.path));
+                Ok(Self { entries })
+            }
+        }
+    };
+}
+
+cached_schema! {
+    OfficialItem,
+    RecipeItem,
+    Area,
+    Report,
+    RankingAllMonthly,
+    RankingSectionMonthly,
+    RankingSectionDaily,
+    Sale,
+    Request,
+    ShopSummary,
+    Shop,
+    People,
+    RequestReport,
+    AreaSummary,
+}
+
+/// キャッシュ1つ分の情報
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    pub schema: CachedSchema,
+    /// [CacheStore] のキー
+    pub key: PathBuf,
+    /// [CacheStore::location]
+    pub path: PathBuf,
+    /// バイト数
+    pub size: u64,
+    pub compression: Compression,
+    pub fetched_at: SystemTime,
+    /// 最終アクセス時刻 (取得できなければ取得時刻)
+    pub accessed_at: SystemTime,
+    pub age: Duration,
+    /// 期限内 (または確定済み)
+    pub fresh: bool,
+    /// 確定済みで, 今後変化しない
+    pub immutable: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CacheIndex {
+    pub entries: Vec<CacheEntry>,
+}
+
+impl CacheIndex {
+    pub fn total_size(&self) -> u64 {
+        self.entries.iter().map(|e| e.size).sum()
+    }
+}
+
+fn scan<C: Cacheable>(
+    store: &dyn CacheStore,
+    wrap: fn(C) -> CachedSchema,
+    entries: &mut Vec<CacheEntry>,
+) -> std::io::Result<()> {
+    let dir = C::file_dir().map(|dir| dir.as_ref().to_path_buf());
+    for info in store.list(dir.as_deref().unwrap_or(Path::new("")))? {
+        let Some(file_name) = info.key.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let Some(schema) = C::from_file_name(file_name) else {
+            continue;
+        };
+        let age = info.fetched_at.elapsed().unwrap_or_default();
+        let immutable = schema.is_finalized_since(info.fetched_at);
+        entries.push(CacheEntry {
+            fresh: immutable || age < C::min_interval(),
+            immutable,
+            schema: wrap(schema),
+            key: info.key,
+            path: info.location,
+            size: info.size,
+            compression: info.compression,
+            fetched_at: info.fetched_at,
+            accessed_at: info.accessed_at,
+            age,
+        });
+    }
+    Ok(())
+}
+
+impl Display for CacheEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let state = match (self.immutable, self.fresh) {
+            (true, _) => "immutable",
+            (false, true) => "fresh",
+            (false, false) => "stale",
+        };
+        write!(
+            f,
+            "{} [{}] {} bytes, {}s ago",
+            self.schema,
+            state,
+            self.size,
+            self.age.as_secs()
+        )
+    }
+}
+
+impl Display for CacheIndex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{entry}")?;
+        }
+        write!(
+            f,
+            "{} files, {} bytes",
+            self.entries.len(),
+            self.total_size()
+        )
+    }
+}
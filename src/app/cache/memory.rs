@@ -0,0 +1,189 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+use crate::app::cache::Cacheable;
+
+/// プロセス内で共有する読み込み済みの応答
+pub static MEMORY: LazyLock<MemoryCache> = LazyLock::new(MemoryCache::default);
+
+/// 確定済みの応答を保持する数の既定値
+pub const DEFAULT_CAPACITY: usize = 64;
+
+type Key = (PathBuf, TypeId, PathBuf);
+
+#[derive(Debug)]
+struct Entry {
+    value: Arc<dyn Any + Send + Sync>,
+    /// `None` の場合は確定済みで期限切れにならない
+    expires_at: Option<SystemTime>,
+    /// 最後に使った順番
+    last_used: u64,
+}
+
+impl Entry {
+    fn is_fresh(&self, now: SystemTime) -> bool {
+        self.expires_at.is_none_or(|t| now < t)
+    }
+
+    fn is_finalized(&self) -> bool {
+        self.expires_at.is_none()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    map: HashMap<Key, Entry>,
+    /// 使う度に増やす
+    tick: u64,
+}
+
+impl Entries {
+    fn tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    /// 確定済みのものが `capacity` を超えた分を最後に使ったのが古い順に捨てる
+    fn evict(&mut self, capacity: usize) {
+        let finalized = self.map.values().filter(|e| e.is_finalized()).count();
+        for _ in capacity..finalized {
+            let oldest = (self.map.iter())
+                .filter(|(_, e)| e.is_finalized())
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(key) = oldest {
+                self.map.remove(&key);
+            }
+        }
+    }
+}
+
+/// パース済みの応答を期限内だけ保持する
+///
+/// キーは (キャッシュの保存先, スキーマ, パラメータ) で, 値は [Arc] で共有する.
+/// 確定済みの応答は期限切れにならないので, 最大 `capacity` 個まで最近使ったものを残す
+#[derive(Debug)]
+pub struct MemoryCache {
+    entries: Mutex<Entries>,
+    capacity: usize,
+}
+
+impl Default for MemoryCache {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+fn key<S: Cacheable + 'static>(cache_root: &Path, schema: &S) -> Key {
+    (
+        cache_root.to_path_buf(),
+        TypeId::of::<S>(),
+        schema.file_path(),
+    )
+}
+
+impl MemoryCache {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::default(),
+            capacity,
+        }
+    }
+
+    fn entries(&self) -> MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 期限内のものだけを返す
+    pub fn get<S>(&self, cache_root: &Path, schema: &S) -> Option<Arc<S::Response>>
+    where
+        S: Cacheable + 'static,
+        S::Response: Send + Sync + 'static,
+    {
+        let mut entries = self.entries();
+        let tick = entries.tick();
+        let entry = entries.map.get_mut(&key(cache_root, schema))?;
+        if !entry.is_fresh(SystemTime::now()) {
+            return None;
+        }
+        entry.last_used = tick;
+        Arc::clone(&entry.value).downcast().ok()
+    }
+
+    /// `fetched_at` に取得した `value` を保持する, ついでに期限切れのものと容量を超えたものを捨てる
+    pub fn insert<S>(
+        &self,
+        cache_root: &Path,
+        schema: &S,
+        value: Arc<S::Response>,
+        fetched_at: SystemTime,
+    ) where
+        S: Cacheable + 'static,
+        S::Response: Send + Sync + 'static,
+    {
+        let expires_at =
+            (!schema.is_finalized_since(fetched_at)).then(|| fetched_at + S::min_interval());
+        let now = SystemTime::now();
+        let mut entries = self.entries();
+        let last_used = entries.tick();
+        entries.map.retain(|_, entry| entry.is_fresh(now));
+        let entry = Entry {
+            value,
+            expires_at,
+            last_used,
+        };
+        entries.map.insert(key(cache_root, schema), entry);
+        entries.evict(self.capacity);
+    }
+
+    pub fn remove<S: Cacheable + 'static>(&self, cache_root: &Path, schema: &S) {
+        self.entries().map.remove(&key(cache_root, schema));
+    }
+
+    pub fn clear(&self) {
+        self.entries().map.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries().map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries().map.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::model::ranking::EPOCH;
+    use crate::api::model::report;
+    use crate::api::schema::Report;
+
+    fn report(days: u64) -> Report {
+        Report(EPOCH + chrono::Days::new(days))
+    }
+
+    #[test]
+    fn finalized_entries_are_bounded() {
+        let memory = MemoryCache::with_capacity(2);
+        let root = Path::new("cache");
+        let json = r#"{"system":{"item":{}},"user":{"item":{}},"request":{"item":{}},"area":{}}"#;
+        let value = || Arc::new(serde_json::from_str::<report::Response>(json).unwrap());
+        let now = SystemTime::now();
+
+        memory.insert(root, &report(0), value(), now);
+        memory.insert(root, &report(1), value(), now);
+        // 最近使ったものは残る
+        assert!(memory.get(root, &report(0)).is_some());
+        memory.insert(root, &report(2), value(), now);
+
+        assert_eq!(memory.len(), 2);
+        assert!(memory.get(root, &report(0)).is_some());
+        assert!(memory.get(root, &report(1)).is_none());
+        assert!(memory.get(root, &report(2)).is_some());
+    }
+}
@@ -0,0 +1,95 @@
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::app::api_client::{ResponseHeaders, Validators};
+use crate::app::cache::file::{with_suffix, write_atomic};
+
+/// キャッシュファイルに付随するメタデータ (`<cache file>.meta`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheMeta {
+    /// 取得 (または再検証) した時刻
+    pub fetched_at: DateTime<Utc>,
+    /// サーバーの `Date`
+    #[serde(default)]
+    pub server_date: Option<String>,
+    /// サーバーの `Last-Modified`
+    #[serde(default)]
+    pub last_modified: Option<String>,
+    /// サーバーの `ETag`
+    #[serde(default)]
+    pub etag: Option<String>,
+    /// 保存したキャッシュ本体のバイト数
+    pub content_length: u64,
+    /// [Cacheable::cache_version](super::Cacheable::cache_version)
+    pub schema_version: u32,
+}
+
+impl CacheMeta {
+    pub fn new(headers: ResponseHeaders, content_length: u64, schema_version: u32) -> Self {
+        Self {
+            fetched_at: Utc::now(),
+            server_date: headers.date,
+            last_modified: headers.last_modified,
+            etag: headers.etag,
+            content_length,
+            schema_version,
+        }
+    }
+
+    /// 304 を受けて取得時刻とヘッダを更新する
+    pub fn revalidated(self, headers: ResponseHeaders) -> Self {
+        Self {
+            fetched_at: Utc::now(),
+            server_date: headers.date.or(self.server_date),
+            last_modified: headers.last_modified.or(self.last_modified),
+            etag: headers.etag.or(self.etag),
+            ..self
+        }
+    }
+
+    pub fn fetched_at(&self) -> SystemTime {
+        self.fetched_at.into()
+    }
+
+    pub fn validators(&self) -> Validators {
+        Validators {
+            etag: self.etag.clone(),
+            last_modified: self.last_modified.clone(),
+        }
+    }
+
+    pub fn path_for(cache_file: &Path) -> PathBuf {
+        with_suffix(cache_file, ".meta")
+    }
+
+    /// 無い, または読めない場合は `None`
+    pub fn load(cache_file: &Path) -> Option<Self> {
+        let bytes = std::fs::read(Self::path_for(cache_file)).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    /// メタデータの取得時刻, 無ければファイルの更新時刻
+    pub fn timestamp(cache_file: &Path) -> std::io::Result<SystemTime> {
+        match Self::load(cache_file) {
+            Some(meta) => Ok(meta.fetched_at()),
+            None => std::fs::metadata(cache_file)?.modified(),
+        }
+    }
+
+    pub fn save(&self, cache_file: &Path) -> std::io::Result<()> {
+        let bytes = serde_json::to_vec_pretty(self)?;
+        write_atomic(&Self::path_for(cache_file), &bytes)
+    }
+}
+
+/// キャッシュファイルとメタデータを削除する
+pub fn remove_with_meta(cache_file: &Path) -> std::io::Result<()> {
+    std::fs::remove_file(cache_file)?;
+    match std::fs::remove_file(CacheMeta::path_for(cache_file)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
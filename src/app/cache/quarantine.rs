@@ -0,0 +1,183 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::app::cache::file::write_atomic;
+
+/// 壊れていたデータの出所
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Corruption {
+    /// 保存済みのキャッシュファイルが読めなかった
+    Cache,
+    /// サーバーの応答が読めなかった
+    Response,
+}
+
+/// 隔離の記録 (`quarantine/log.jsonl` の1行)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuarantineRecord {
+    pub at: DateTime<Utc>,
+    pub kind: Corruption,
+    /// 元のキャッシュファイル, またはエンドポイント
+    pub origin: String,
+    /// 隔離先
+    pub quarantined: PathBuf,
+    /// 読めなかった箇所
+    pub json_path: String,
+    pub reason: String,
+}
+
+/// 読めなかったデータの隔離場所 (`<cache root>/quarantine`)
+#[derive(Debug, Clone)]
+pub struct Quarantine {
+    dir: PathBuf,
+}
+
+impl Quarantine {
+    pub fn new(cache_root: &Path) -> Self {
+        Self {
+            dir: cache_root.join("quarantine"),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn log_path(&self) -> PathBuf {
+        self.dir.join("log.jsonl")
+    }
+
+    fn destination(&self, at: DateTime<Utc>, file_name: &str) -> PathBuf {
+        let at = at.format("%Y%m%dT%H%M%S%.3f");
+        self.dir.join(format!("{at}_{file_name}.corrupt"))
+    }
+
+    /// 壊れたキャッシュの内容を保存する (元のキャッシュの削除は呼び出し側で行う)
+    pub fn isolate_cache(
+        &self,
+        location: &Path,
+        bytes: &[u8],
+        json_path: &str,
+        reason: &str,
+    ) -> std::io::Result<QuarantineRecord> {
+        std::fs::create_dir_all(&self.dir)?;
+        let at = Utc::now();
+        let file_name = location.file_name().unwrap_or_default().to_string_lossy();
+        let quarantined = self.destination(at, &file_name);
+        write_atomic(&quarantined, bytes)?;
+
+        self.record(QuarantineRecord {
+            at,
+            kind: Corruption::Cache,
+            origin: location.display().to_string(),
+            quarantined,
+            json_path: json_path.to_string(),
+            reason: reason.to_string(),
+        })
+    }
+
+    /// 読めなかった応答を調査用に保存する
+    pub fn keep_response(
+        &self,
+        endpoint: &url::Url,
+        bytes: &[u8],
+        json_path: &str,
+        reason: &str,
+    ) -> std::io::Result<QuarantineRecord> {
+        std::fs::create_dir_all(&self.dir)?;
+        let at = Utc::now();
+        let file_name = endpoint
+            .path_segments()
+            .and_then(|s| s.last())
+            .unwrap_or("response");
+        let quarantined = self.destination(at, file_name);
+        write_atomic(&quarantined, bytes)?;
+
+        self.record(QuarantineRecord {
+            at,
+            kind: Corruption::Response,
+            origin: endpoint.to_string(),
+            quarantined,
+            json_path: json_path.to_string(),
+            reason: reason.to_string(),
+        })
+    }
+
+    fn record(&self, record: QuarantineRecord) -> std::io::Result<QuarantineRecord> {
+        eprintln!(
+            "quarantined: {} -> {:?} ({} at {})",
+            record.origin, record.quarantined, record.reason, record.json_path
+        );
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.log_path())?
+            .write_all(&line)?;
+        Ok(record)
+    }
+
+    /// 古い順, ログが無ければ空
+    pub fn records(&self) -> std::io::Result<Vec<QuarantineRecord>> {
+        let log = match std::fs::read_to_string(self.log_path()) {
+            Ok(log) => log,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        Ok(log
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    pub fn health(&self) -> std::io::Result<CacheHealth> {
+        let records = self.records()?;
+        let mut health = CacheHealth::default();
+        for record in &records {
+            *health.count.entry(record.kind).or_default() += 1;
+            *health.by_origin.entry(record.origin.clone()).or_default() += 1;
+        }
+        health.last = records.into_iter().last();
+        Ok(health)
+    }
+}
+
+/// 隔離の集計
+#[derive(Debug, Default, Clone)]
+pub struct CacheHealth {
+    pub count: BTreeMap<Corruption, usize>,
+    /// 元のキャッシュファイル, またはエンドポイント毎の件数
+    pub by_origin: BTreeMap<String, usize>,
+    pub last: Option<QuarantineRecord>,
+}
+
+impl CacheHealth {
+    pub fn count(&self, kind: Corruption) -> usize {
+        self.count.get(&kind).copied().unwrap_or(0)
+    }
+}
+
+impl Display for CacheHealth {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "corrupt cache: {}, invalid response: {}",
+            self.count(Corruption::Cache),
+            self.count(Corruption::Response)
+        )?;
+        for (origin, count) in &self.by_origin {
+            writeln!(f, "\t{origin}: {count}")?;
+        }
+        if let Some(last) = &self.last {
+            write!(f, "last: {} {} ({})", last.at, last.origin, last.reason)?;
+        }
+        Ok(())
+    }
+}
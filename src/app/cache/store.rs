@@ -0,0 +1,321 @@
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::cache::Cacheable;
+use crate::app::cache::compression::{self, Compression};
+use crate::app::cache::file::{with_suffix, write_atomic};
+use crate::app::cache::meta::{self, CacheMeta};
+use crate::app::config::CONFIG;
+
+/// キャッシュの保存先の種類 (設定ファイルの `"cache_backend"`)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// スキーマ毎のJSONファイル ([FsStore])
+    #[default]
+    Files,
+    /// 1つのSQLiteデータベース (`sqlite` feature が必要)
+    Sqlite,
+}
+
+/// 保存済みのキャッシュ
+#[derive(Debug, Clone)]
+pub struct Stored {
+    /// 展開済みの内容
+    pub bytes: Vec<u8>,
+    /// メタデータを保存する前のキャッシュでは `None`
+    pub meta: Option<CacheMeta>,
+    /// 保存した時刻 (ファイルの更新時刻)
+    pub modified: SystemTime,
+}
+
+impl Stored {
+    /// メタデータの取得時刻, 無ければ保存した時刻
+    pub fn fetched_at(&self) -> SystemTime {
+        self.meta
+            .as_ref()
+            .map_or(self.modified, CacheMeta::fetched_at)
+    }
+}
+
+/// 展開しながら読む保存済みのキャッシュ
+pub struct StoredReader {
+    pub reader: Box<dyn Read + Send>,
+    pub meta: Option<CacheMeta>,
+    pub modified: SystemTime,
+}
+
+impl StoredReader {
+    /// [Stored::fetched_at]
+    pub fn fetched_at(&self) -> SystemTime {
+        self.meta
+            .as_ref()
+            .map_or(self.modified, CacheMeta::fetched_at)
+    }
+}
+
+impl Debug for StoredReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StoredReader")
+            .field("meta", &self.meta)
+            .field("modified", &self.modified)
+            .finish_non_exhaustive()
+    }
+}
+
+/// 一覧用の情報
+#[derive(Debug, Clone)]
+pub struct StoredInfo {
+    pub key: PathBuf,
+    /// [CacheStore::location]
+    pub location: PathBuf,
+    /// 保存しているバイト数 (圧縮後)
+    pub size: u64,
+    pub compression: Compression,
+    pub fetched_at: SystemTime,
+    /// 最終アクセス時刻 (取得できなければ取得時刻)
+    pub accessed_at: SystemTime,
+}
+
+/// キャッシュの保存先
+///
+/// キーは [Cacheable::file_path] (圧縮形式の拡張子を含まない相対パス)
+pub trait CacheStore: Debug + Send + Sync {
+    /// ログやエラーに表示する `key` の場所
+    fn location(&self, key: &Path) -> PathBuf;
+
+    /// 無ければ `None`
+    fn read(&self, key: &Path) -> std::io::Result<Option<Stored>>;
+
+    /// 全体を展開せずに読む, 無ければ `None`
+    fn reader(&self, key: &Path) -> std::io::Result<Option<StoredReader>> {
+        Ok(self.read(key)?.map(|stored| StoredReader {
+            reader: Box::new(std::io::Cursor::new(stored.bytes)),
+            meta: stored.meta,
+            modified: stored.modified,
+        }))
+    }
+
+    /// 無い, または読めない場合は `None`
+    fn read_meta(&self, key: &Path) -> Option<CacheMeta>;
+
+    /// `bytes` は `compression` で圧縮済みのもの
+    fn write(
+        &self,
+        key: &Path,
+        bytes: &[u8],
+        compression: Compression,
+        meta: &CacheMeta,
+    ) -> std::io::Result<()>;
+
+    fn write_meta(&self, key: &Path, meta: &CacheMeta) -> std::io::Result<()>;
+
+    fn remove(&self, key: &Path) -> std::io::Result<()>;
+
+    /// `dir` 直下 (ルート直下は空のパス) の全てのキャッシュ
+    fn list(&self, dir: &Path) -> std::io::Result<Vec<StoredInfo>>;
+
+    /// 同じキーへの取得を (プロセスを跨いで) 排他するためのロックファイル
+    fn lock_path(&self, key: &Path) -> PathBuf;
+}
+
+/// 保存済みのスキーマ `S` のパラメータ
+pub fn cached<S: Cacheable>(store: &dyn CacheStore) -> std::io::Result<Vec<S>> {
+    let dir = S::file_dir().map(|dir| dir.as_ref().to_path_buf());
+    let infos = store.list(dir.as_deref().unwrap_or(Path::new("")))?;
+    Ok(infos
+        .iter()
+        .filter_map(|info| S::from_file_name(info.key.file_name()?.to_str()?))
+        .collect())
+}
+
+static STORES: LazyLock<Mutex<HashMap<PathBuf, Arc<dyn CacheStore>>>> =
+    LazyLock::new(Default::default);
+
+/// `cache_root` に対して設定された保存先を開く (同じ場所は使い回す)
+///
+/// SQLiteを開けない場合はファイルに保存する
+pub fn open(cache_root: &Path) -> Arc<dyn CacheStore> {
+    let mut stores = STORES.lock().unwrap_or_else(|e| e.into_inner());
+    let store = stores
+        .entry(cache_root.to_path_buf())
+        .or_insert_with(|| open_backend(cache_root, CONFIG.cache_backend));
+    Arc::clone(store)
+}
+
+fn open_backend(cache_root: &Path, backend: Backend) -> Arc<dyn CacheStore> {
+    match backend {
+        Backend::Files => Arc::new(FsStore::new(cache_root)),
+        #[cfg(feature = "sqlite")]
+        Backend::Sqlite => match sqlite::SqliteStore::open(cache_root) {
+            Ok(store) => Arc::new(store),
+            Err(e) => {
+                eprintln!("cannot open sqlite cache in {:?}: {e}", cache_root);
+                Arc::new(FsStore::new(cache_root))
+            }
+        },
+        #[cfg(not(feature = "sqlite"))]
+        Backend::Sqlite => {
+            eprintln!("built without the `sqlite` feature, use files instead");
+            Arc::new(FsStore::new(cache_root))
+        }
+    }
+}
+
+/// スキーマ毎のJSONファイル (`<cache root>/<file path>[.gz]`) とメタデータ (`.meta`)
+#[derive(Debug, Clone)]
+pub struct FsStore {
+    root: PathBuf,
+}
+
+impl FsStore {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 現在の形式が無ければ以前の形式 (非圧縮) も探す
+    fn existing_path(&self, key: &Path) -> Option<PathBuf> {
+        let path = self.root.join(key);
+        [Compression::Gzip, Compression::None]
+            .iter()
+            .map(|c| c.apply(&path))
+            .find(|path| path.exists())
+    }
+}
+
+impl CacheStore for FsStore {
+    fn location(&self, key: &Path) -> PathBuf {
+        self.existing_path(key)
+            .unwrap_or_else(|| self.root.join(key))
+    }
+
+    fn read(&self, key: &Path) -> std::io::Result<Option<Stored>> {
+        let Some(path) = self.existing_path(key) else {
+            return Ok(None);
+        };
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Some(Stored {
+            bytes: compression::decompress(bytes)?,
+            meta: CacheMeta::load(&path),
+            modified: std::fs::metadata(&path)?.modified()?,
+        }))
+    }
+
+    fn reader(&self, key: &Path) -> std::io::Result<Option<StoredReader>> {
+        let Some(path) = self.existing_path(key) else {
+            return Ok(None);
+        };
+        let file = match std::fs::File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let modified = file.metadata()?.modified()?;
+        Ok(Some(StoredReader {
+            reader: compression::decompress_reader(BufReader::new(file))?,
+            meta: CacheMeta::load(&path),
+            modified,
+        }))
+    }
+
+    fn read_meta(&self, key: &Path) -> Option<CacheMeta> {
+        CacheMeta::load(&self.existing_path(key)?)
+    }
+
+    fn write(
+        &self,
+        key: &Path,
+        bytes: &[u8],
+        compression: Compression,
+        meta: &CacheMeta,
+    ) -> std::io::Result<()> {
+        let base = self.root.join(key);
+        let path = compression.apply(&base);
+        std::fs::create_dir_all(path.parent().expect("invalid cache dir"))?;
+        write_atomic(&path, bytes)?;
+        meta.save(&path)?;
+        println!("Save cache: {:?}", path);
+
+        // 圧縮形式を変える前のファイルは消しておく
+        for other in [Compression::None, Compression::Gzip] {
+            let other = other.apply(&base);
+            if other != path && other.exists() {
+                let _ = meta::remove_with_meta(&other);
+            }
+        }
+        Ok(())
+    }
+
+    fn write_meta(&self, key: &Path, meta: &CacheMeta) -> std::io::Result<()> {
+        match self.existing_path(key) {
+            Some(path) => meta.save(&path),
+            None => Err(std::io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn remove(&self, key: &Path) -> std::io::Result<()> {
+        match self.existing_path(key) {
+            Some(path) => meta::remove_with_meta(&path),
+            None => Ok(()),
+        }
+    }
+
+    fn list(&self, dir: &Path) -> std::io::Result<Vec<StoredInfo>> {
+        let read_dir = match std::fs::read_dir(self.root.join(dir)) {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut infos = Vec::new();
+        for entry in read_dir {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if !metadata.is_file() {
+                continue;
+            }
+            let file_name = entry.file_name();
+            let Some((file_name, compression)) = file_name.to_str().map(compression::strip_suffix)
+            else {
+                continue;
+            };
+            if !file_name.ends_with(".json") {
+                continue;
+            }
+            let location = entry.path();
+            let fetched_at = CacheMeta::timestamp(&location)?;
+            infos.push(StoredInfo {
+                key: dir.join(file_name),
+                size: metadata.len(),
+                compression,
+                fetched_at,
+                accessed_at: metadata.accessed().unwrap_or(fetched_at).max(fetched_at),
+                location,
+            });
+        }
+        Ok(infos)
+    }
+
+    fn lock_path(&self, key: &Path) -> PathBuf {
+        with_suffix(&self.root.join(key), ".lock")
+    }
+}
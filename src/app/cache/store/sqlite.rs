@@ -0,0 +1,351 @@
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use itertools::Itertools;
+use rusqlite::{Connection, OptionalExtension, params};
+
+use super::{CacheStore, Stored, StoredInfo, StoredReader};
+use crate::app::cache::compression::{self, Compression};
+use crate::app::cache::file::with_suffix;
+use crate::app::cache::meta::CacheMeta;
+
+const DB_FILE_NAME: &str = "cache.sqlite3";
+
+/// 圧縮したままの内容, メタデータ, 取得時刻
+type Row = (Vec<u8>, Option<CacheMeta>, SystemTime);
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS cache_entry (
+    key         TEXT PRIMARY KEY,
+    dir         TEXT NOT NULL,
+    payload     BLOB NOT NULL,
+    gzip        INTEGER NOT NULL,
+    meta        TEXT NOT NULL,
+    fetched_at  INTEGER NOT NULL,
+    accessed_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS cache_entry_dir ON cache_entry (dir);
+";
+
+/// 全てのキャッシュを1つのSQLiteデータベース (`<cache root>/cache.sqlite3`) に保存する
+///
+/// `dir` 列は [Cacheable::file_dir](crate::app::cache::Cacheable::file_dir) なので,
+/// 例えば取得済みのレポートの日付は `SELECT key FROM cache_entry WHERE dir = 'report'` で引ける
+#[derive(Debug)]
+pub struct SqliteStore {
+    path: PathBuf,
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    pub fn open(cache_root: &Path) -> rusqlite::Result<Self> {
+        let _ = std::fs::create_dir_all(cache_root);
+        let path = cache_root.join(DB_FILE_NAME);
+        let connection = Connection::open(&path)?;
+        connection.busy_timeout(Duration::from_secs(5))?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self {
+            path,
+            connection: Mutex::new(connection),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn connection(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.connection.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// ついでに最終アクセス時刻を更新する
+    fn read_row(&self, key: &Path) -> std::io::Result<Option<Row>> {
+        let key = key_str(key);
+        let connection = self.connection();
+        let row = connection
+            .query_row(
+                "SELECT payload, meta, fetched_at FROM cache_entry WHERE key = ?1",
+                params![key],
+                |row| {
+                    Ok((
+                        row.get::<_, Vec<u8>>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get(2)?,
+                    ))
+                },
+            )
+            .optional()
+            .map_err(io_error)?;
+        let Some((payload, meta, fetched_at)) = row else {
+            return Ok(None);
+        };
+        connection
+            .execute(
+                "UPDATE cache_entry SET accessed_at = ?2 WHERE key = ?1",
+                params![key, unix_secs(SystemTime::now())],
+            )
+            .map_err(io_error)?;
+        Ok(Some((
+            payload,
+            parse_meta(&meta),
+            from_unix_secs(fetched_at),
+        )))
+    }
+}
+
+/// OSに依らず `/` 区切り
+fn key_str(key: &Path) -> String {
+    key.iter().map(|s| s.to_string_lossy()).join("/")
+}
+
+fn unix_secs(t: SystemTime) -> i64 {
+    t.duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+fn from_unix_secs(secs: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64)
+}
+
+fn io_error(e: rusqlite::Error) -> std::io::Error {
+    std::io::Error::other(e)
+}
+
+fn parse_meta(meta: &str) -> Option<CacheMeta> {
+    serde_json::from_str(meta).ok()
+}
+
+impl CacheStore for SqliteStore {
+    fn location(&self, key: &Path) -> PathBuf {
+        with_suffix(&self.path, &format!("#{}", key_str(key)))
+    }
+
+    fn read(&self, key: &Path) -> std::io::Result<Option<Stored>> {
+        let Some((payload, meta, modified)) = self.read_row(key)? else {
+            return Ok(None);
+        };
+        Ok(Some(Stored {
+            bytes: compression::decompress(payload)?,
+            meta,
+            modified,
+        }))
+    }
+
+    fn reader(&self, key: &Path) -> std::io::Result<Option<StoredReader>> {
+        let Some((payload, meta, modified)) = self.read_row(key)? else {
+            return Ok(None);
+        };
+        Ok(Some(StoredReader {
+            reader: compression::decompress_reader(Cursor::new(payload))?,
+            meta,
+            modified,
+        }))
+    }
+
+    fn read_meta(&self, key: &Path) -> Option<CacheMeta> {
+        let meta: String = self
+            .connection()
+            .query_row(
+                "SELECT meta FROM cache_entry WHERE key = ?1",
+                params![key_str(key)],
+                |row| row.get(0),
+            )
+            .ok()?;
+        parse_meta(&meta)
+    }
+
+    fn write(
+        &self,
+        key: &Path,
+        bytes: &[u8],
+        compression: Compression,
+        meta: &CacheMeta,
+    ) -> std::io::Result<()> {
+        let dir = key.parent().map(key_str).unwrap_or_default();
+        let fetched_at = unix_secs(meta.fetched_at());
+        self.connection()
+            .execute(
+                "INSERT OR REPLACE INTO cache_entry
+                    (key, dir, payload, gzip, meta, fetched_at, accessed_at)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)",
+                params![
+                    key_str(key),
+                    dir,
+                    bytes,
+                    compression == Compression::Gzip,
+                    serde_json::to_string(meta)?,
+                    fetched_at,
+                ],
+            )
+            .map_err(io_error)?;
+        println!("Save cache: {:?}", self.location(key));
+        Ok(())
+    }
+
+    fn write_meta(&self, key: &Path, meta: &CacheMeta) -> std::io::Result<()> {
+        let updated = self
+            .connection()
+            .execute(
+                "UPDATE cache_entry SET meta = ?2, fetched_at = ?3 WHERE key = ?1",
+                params![
+                    key_str(key),
+                    serde_json::to_string(meta)?,
+                    unix_secs(meta.fetched_at())
+                ],
+            )
+            .map_err(io_error)?;
+        match updated {
+            0 => Err(std::io::ErrorKind::NotFound.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn remove(&self, key: &Path) -> std::io::Result<()> {
+        self.connection()
+            .execute(
+                "DELETE FROM cache_entry WHERE key = ?1",
+                params![key_str(key)],
+            )
+            .map(|_| ())
+            .map_err(io_error)
+    }
+
+    fn list(&self, dir: &Path) -> std::io::Result<Vec<StoredInfo>> {
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare(
+                "SELECT key, length(payload), gzip, fetched_at, accessed_at
+                    FROM cache_entry WHERE dir = ?1",
+            )
+            .map_err(io_error)?;
+        let rows = statement
+            .query_map(params![key_str(dir)], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, bool>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, i64>(4)?,
+                ))
+            })
+            .map_err(io_error)?;
+
+        rows.map(|row| {
+            let (key, size, gzip, fetched_at, accessed_at) = row.map_err(io_error)?;
+            let key = PathBuf::from_iter(key.split('/'));
+            let fetched_at = from_unix_secs(fetched_at);
+            Ok(StoredInfo {
+                location: self.location(&key),
+                key,
+                size: size as u64,
+                compression: if gzip {
+                    Compression::Gzip
+                } else {
+                    Compression::None
+                },
+                fetched_at,
+                accessed_at: from_unix_secs(accessed_at).max(fetched_at),
+            })
+        })
+        .collect()
+    }
+
+    fn lock_path(&self, key: &Path) -> PathBuf {
+        let dir = with_suffix(&self.path, ".locks");
+        with_suffix(&dir.join(key), ".lock")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+    use crate::app::api_client::ResponseHeaders;
+
+    fn meta(etag: &str, content_length: u64) -> CacheMeta {
+        let headers = ResponseHeaders {
+            etag: Some(etag.to_string()),
+            ..Default::default()
+        };
+        CacheMeta::new(headers, content_length, 1)
+    }
+
+    #[test]
+    fn read_write() {
+        let root = std::env::temp_dir().join(format!("so2_tool_sqlite_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let store = SqliteStore::open(&root).unwrap();
+        assert_eq!(store.path(), root.join(DB_FILE_NAME));
+
+        let report = Path::new("report").join("report_2024-03-10.json");
+        let sale = Path::new("sale.json");
+        let json = br#"{"a":1}"#;
+        let gzip = Compression::Gzip.compress(json).unwrap();
+        (store.write(&report, &gzip, Compression::Gzip, &meta("r", 10))).unwrap();
+        (store.write(sale, b"[]", Compression::None, &meta("s", 2))).unwrap();
+
+        // キーは `/` 区切り, `dir` 列は親ディレクトリ
+        let location = store.location(&report);
+        assert!(location.ends_with("cache.sqlite3#report/report_2024-03-10.json"));
+        let dirs: Vec<(String, String)> = {
+            let connection = store.connection();
+            let mut statement =
+                (connection.prepare("SELECT key, dir FROM cache_entry ORDER BY key")).unwrap();
+            let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)));
+            rows.unwrap().map(Result::unwrap).collect()
+        };
+        assert_eq!(
+            dirs,
+            [
+                ("report/report_2024-03-10.json".into(), "report".into()),
+                ("sale.json".into(), "".into()),
+            ]
+        );
+
+        // 圧縮したものは展開して返す
+        let stored = store.read(&report).unwrap().unwrap();
+        assert_eq!(stored.bytes, json);
+        assert_eq!(stored.meta.unwrap().etag.as_deref(), Some("r"));
+        let mut bytes = Vec::new();
+        let mut reader = store.reader(&report).unwrap().unwrap().reader;
+        reader.read_to_end(&mut bytes).unwrap();
+        assert_eq!(bytes, json);
+        assert_eq!(store.read(sale).unwrap().unwrap().bytes, b"[]");
+        assert!(store.read(Path::new("area.json")).unwrap().is_none());
+
+        let mut revalidated = store.read_meta(&report).unwrap();
+        revalidated.fetched_at -= chrono::TimeDelta::days(1);
+        revalidated.etag = Some("r2".to_string());
+        store.write_meta(&report, &revalidated).unwrap();
+        let stored = store.read_meta(&report).unwrap();
+        assert_eq!(stored.etag.as_deref(), Some("r2"));
+        assert_eq!(stored.fetched_at(), revalidated.fetched_at());
+
+        let infos = store.list(Path::new("report")).unwrap();
+        assert_eq!(infos.len(), 1);
+        assert_eq!(infos[0].key, report);
+        assert_eq!(infos[0].size, gzip.len() as u64);
+        assert_eq!(infos[0].compression, Compression::Gzip);
+        assert_eq!(
+            unix_secs(infos[0].fetched_at),
+            unix_secs(revalidated.fetched_at())
+        );
+        let infos = store.list(Path::new("")).unwrap();
+        assert_eq!(infos.len(), 1);
+        assert_eq!(infos[0].key, sale);
+        assert_eq!(infos[0].compression, Compression::None);
+
+        store.remove(&report).unwrap();
+        assert!(store.read(&report).unwrap().is_none());
+        assert!(store.read_meta(&report).is_none());
+        assert!(store.list(Path::new("report")).unwrap().is_empty());
+        let e = store.write_meta(&report, &revalidated).unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::NotFound);
+
+        drop(store);
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,39 @@
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::cache::store::Backend;
+
+pub const APP_NAME: &str = env!("CARGO_PKG_NAME");
+
+/// 設定ファイル (`<config_dir>/so2_tool/config.json`) の内容
+///
+/// ファイルが無い, または読めない場合は全て既定値
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub cache_root: Option<PathBuf>,
+    /// `"files"` (既定) または `"sqlite"`
+    pub cache_backend: Backend,
+}
+
+pub static CONFIG: LazyLock<Config> = LazyLock::new(Config::load);
+
+impl Config {
+    pub fn file_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(APP_NAME).join("config.json"))
+    }
+
+    pub fn load() -> Self {
+        let Some(path) = Self::file_path() else {
+            return Self::default();
+        };
+        let Ok(bytes) = std::fs::read(&path) else {
+            return Self::default();
+        };
+        serde_json::from_slice(&bytes)
+            .inspect_err(|e| eprintln!("invalid config {:?}: {e}", path))
+            .unwrap_or_default()
+    }
+}
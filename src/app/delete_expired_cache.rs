@@ -1,53 +1,340 @@
-use std::path::Path;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::time::Duration;
 
-use crate::api::schema::*;
+use itertools::Itertools;
 
-use super::cache::Cacheable;
+use super::cache::index::{CacheEntry, CacheIndex};
+use super::cache::store::CacheStore;
 
-pub fn delete(cache_dir: &Path) -> std::io::Result<()> {
-    let helper = Helper::new(cache_dir);
+/// スキーマ毎の保持方針
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Retention {
+    /// 取得からこれ以上経ったものを削除 (確定済みでも)
+    pub max_age: Option<Duration>,
+    /// 新しい方からこの件数を残す
+    pub max_count: Option<usize>,
+}
+
+/// 削除の方針
+#[derive(Debug, Clone, Default)]
+pub struct CleanupPolicy {
+    /// key: [CachedSchema::name](super::cache::index::CachedSchema::name)
+    pub retention: HashMap<&'static str, Retention>,
+    /// キャッシュ全体の上限 (バイト), 超えた分は最終アクセスが古いものから削除
+    pub size_budget: Option<u64>,
+    /// 削除せずに, 削除対象だけを返す
+    pub dry_run: bool,
+}
+
+impl CleanupPolicy {
+    pub fn dry_run(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+}
 
-    // helper.delete::<Report>();
-    helper.delete::<RankingAllMonthly>()?;
-    helper.delete::<RankingSectionMonthly>()?;
-    helper.delete::<RankingSectionDaily>()?;
-    helper.delete::<RequestReport>()?;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemovalReason {
+    /// 日付毎のキャッシュで期限切れ (確定済みのものは残す)
+    Expired,
+    /// [Retention::max_age] 超過
+    TooOld,
+    /// [Retention::max_count] 超過
+    OverCount,
+    /// [CleanupPolicy::size_budget] 超過
+    OverBudget,
+}
 
-    Ok(())
+#[derive(Debug, Clone)]
+pub struct Removal {
+    /// [CacheStore] のキー
+    pub key: PathBuf,
+    pub path: PathBuf,
+    pub size: u64,
+    pub reason: RemovalReason,
 }
 
-struct Helper<'a> {
-    path: &'a Path,
+#[derive(Debug, Default)]
+pub struct CleanupReport {
+    pub dry_run: bool,
+    pub removed: Vec<Removal>,
+    /// 削除に失敗したもの
+    pub failed: Vec<(PathBuf, std::io::Error)>,
+    pub kept: usize,
+    pub kept_bytes: u64,
 }
 
-impl<'a> Helper<'a> {
-    fn new(path: &'a Path) -> Self {
-        Self { path }
+impl CleanupReport {
+    pub fn freed_bytes(&self) -> u64 {
+        self.removed.iter().map(|r| r.size).sum()
     }
+}
 
-    fn delete<C: Cacheable>(&self) -> std::io::Result<()> {
-        let Some(dir) = C::file_dir() else {
-            return Ok(());
-        };
-        let dir = dir.as_ref();
+pub fn delete(store: &dyn CacheStore, policy: &CleanupPolicy) -> std::io::Result<CleanupReport> {
+    let index = CacheIndex::scan(store)?;
+    let (removals, kept) = plan(index.entries, policy);
 
-        eprintln!("delete dir: {:?}", dir);
+    let mut report = CleanupReport {
+        dry_run: policy.dry_run,
+        kept: kept.len(),
+        kept_bytes: kept.iter().map(|e| e.size).sum(),
+        ..Default::default()
+    };
+    for removal in removals {
+        if !policy.dry_run {
+            if let Err(e) = store.remove(&removal.key) {
+                report.failed.push((removal.path, e));
+                continue;
+            }
+        }
+        report.removed.push(removal);
+    }
+    Ok(report)
+}
 
-        let dir = self.path.join(dir);
+fn plan(entries: Vec<CacheEntry>, policy: &CleanupPolicy) -> (Vec<Removal>, Vec<CacheEntry>) {
+    let mut removals = Vec::new();
+    let mut remove = |entry: CacheEntry, reason| {
+        removals.push(Removal {
+            key: entry.key,
+            path: entry.path,
+            size: entry.size,
+            reason,
+        })
+    };
+    let retention = |entry: &CacheEntry| {
+        (policy.retention)
+            .get(entry.schema.name())
+            .copied()
+            .unwrap_or_default()
+    };
 
-        for entry in std::fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
+    let mut kept = Vec::new();
+    for entry in entries {
+        let retention = retention(&entry);
+        if entry.schema.file_dir().is_some() && !entry.fresh {
+            remove(entry, RemovalReason::Expired);
+        } else if retention.max_age.is_some_and(|max_age| entry.age > max_age) {
+            remove(entry, RemovalReason::TooOld);
+        } else {
+            kept.push(entry);
+        }
+    }
 
-            if path.extension().is_some_and(|ext| ext == "json") && {
-                let timestamp = entry.metadata()?.modified()?;
-                timestamp.elapsed().is_ok_and(|t| t > C::min_interval())
-            } {
-                std::fs::remove_file(&path)?;
-                eprintln!("file removed: {:?}", path);
+    let groups = kept.into_iter().into_group_map_by(|e| e.schema.name());
+    let mut kept = Vec::new();
+    for (_, mut group) in groups {
+        group.sort_by_key(|e| std::cmp::Reverse(e.fetched_at));
+        let max_count = retention(&group[0]).max_count.unwrap_or(usize::MAX);
+        for (i, entry) in group.into_iter().enumerate() {
+            if i < max_count {
+                kept.push(entry);
+            } else {
+                remove(entry, RemovalReason::OverCount);
             }
         }
+    }
+
+    if let Some(budget) = policy.size_budget {
+        kept.sort_by_key(|e| std::cmp::Reverse(e.accessed_at));
+        let mut total = 0;
+        let (within, over): (Vec<_>, Vec<_>) = kept.into_iter().partition(|e| {
+            total += e.size;
+            total <= budget
+        });
+        over.into_iter()
+            .for_each(|e| remove(e, RemovalReason::OverBudget));
+        kept = within;
+    }
+
+    (removals, kept)
+}
+
+impl Display for CleanupReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let verb = if self.dry_run {
+            "would remove"
+        } else {
+            "removed"
+        };
+        for removal in &self.removed {
+            writeln!(f, "{verb}: {:?} ({:?})", removal.path, removal.reason)?;
+        }
+        for (path, e) in &self.failed {
+            writeln!(f, "failed: {:?} ({e})", path)?;
+        }
+        write!(
+            f,
+            "{verb} {} files ({} bytes), kept {} files ({} bytes)",
+            self.removed.len(),
+            self.freed_bytes(),
+            self.kept,
+            self.kept_bytes
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::api::schema::{Report, Sale};
+    use crate::app::api_client::ResponseHeaders;
+    use crate::app::cache::Cacheable;
+    use crate::app::cache::compression::Compression;
+    use crate::app::cache::index::CachedSchema;
+    use crate::app::cache::meta::CacheMeta;
+    use crate::app::cache::store::FsStore;
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    fn report(day: u32) -> CachedSchema {
+        CachedSchema::Report(Report(NaiveDate::from_ymd_opt(2024, 3, day).unwrap()))
+    }
+
+    /// `hours` 時間前に取得し, `accessed` 時間前に使った `size` バイトのキャッシュ
+    fn entry(schema: CachedSchema, hours: u32, accessed: u32, size: u64) -> CacheEntry {
+        let now = SystemTime::now();
+        let key = PathBuf::from(format!("{schema}"));
+        CacheEntry {
+            schema,
+            path: key.clone(),
+            key,
+            size,
+            compression: Compression::None,
+            fetched_at: now - HOUR * hours,
+            accessed_at: now - HOUR * accessed,
+            age: HOUR * hours,
+            fresh: false,
+            immutable: false,
+        }
+    }
+
+    fn finalized(mut entry: CacheEntry) -> CacheEntry {
+        entry.fresh = true;
+        entry.immutable = true;
+        entry
+    }
+
+    fn reasons(removals: &[Removal]) -> Vec<(PathBuf, RemovalReason)> {
+        let reasons = removals.iter().map(|r| (r.key.clone(), r.reason));
+        reasons.sorted_by(|a, b| a.0.cmp(&b.0)).collect()
+    }
+
+    fn retention(name: &'static str, retention: Retention) -> CleanupPolicy {
+        CleanupPolicy {
+            retention: HashMap::from([(name, retention)]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn expired_and_finalized() {
+        let stale = entry(report(1), 2, 2, 10);
+        let kept = finalized(entry(report(2), 2, 2, 10));
+        // 日付毎でないキャッシュは期限切れでも残す
+        let sale = entry(CachedSchema::Sale(Sale), 2, 2, 10);
+        let stale_key = stale.key.clone();
+
+        let (removals, kept) = plan(vec![stale, kept, sale], &CleanupPolicy::default());
+        assert_eq!(reasons(&removals), [(stale_key, RemovalReason::Expired)]);
+        assert_eq!(kept.len(), 2);
+    }
+
+    #[test]
+    fn max_age() {
+        let policy = retention(
+            "Report",
+            Retention {
+                max_age: Some(HOUR * 24),
+                max_count: None,
+            },
+        );
+        // 確定済みでも削除する
+        let old = finalized(entry(report(1), 25, 0, 10));
+        let recent = finalized(entry(report(2), 23, 0, 10));
+        let old_key = old.key.clone();
+
+        let (removals, kept) = plan(vec![old, recent], &policy);
+        assert_eq!(reasons(&removals), [(old_key, RemovalReason::TooOld)]);
+        assert_eq!(kept.len(), 1);
+    }
+
+    #[test]
+    fn max_count_per_schema() {
+        let policy = retention(
+            "Report",
+            Retention {
+                max_age: None,
+                max_count: Some(2),
+            },
+        );
+        // 取得が古いものから削除する
+        let entries = vec![
+            finalized(entry(report(1), 1, 0, 10)),
+            finalized(entry(report(2), 3, 0, 10)),
+            finalized(entry(report(3), 2, 0, 10)),
+            entry(CachedSchema::Sale(Sale), 0, 0, 10),
+        ];
+        let oldest = entries[1].key.clone();
+
+        let (removals, kept) = plan(entries, &policy);
+        assert_eq!(reasons(&removals), [(oldest, RemovalReason::OverCount)]);
+        assert_eq!(kept.len(), 3);
+    }
+
+    #[test]
+    fn size_budget_by_last_access() {
+        let policy = CleanupPolicy {
+            size_budget: Some(250),
+            ..Default::default()
+        };
+        // 最終アクセスが古いものから削除する
+        let entries = vec![
+            finalized(entry(report(1), 0, 3, 100)),
+            finalized(entry(report(2), 9, 1, 100)),
+            finalized(entry(report(3), 0, 2, 100)),
+        ];
+        let least_recent = entries[0].key.clone();
+
+        let (removals, kept) = plan(entries, &policy);
+        assert_eq!(
+            reasons(&removals),
+            [(least_recent, RemovalReason::OverBudget)]
+        );
+        assert_eq!(kept.iter().map(|e| e.size).sum::<u64>(), 200);
+    }
+
+    #[test]
+    fn dry_run_keeps_files() {
+        let root = std::env::temp_dir().join(format!("so2_tool_cleanup_{}", std::process::id()));
+        let store = FsStore::new(&root);
+        let key = Sale.file_path();
+        let bytes = Sale::compression().compress(b"[]").unwrap();
+        let mut meta = CacheMeta::new(ResponseHeaders::default(), 0, Sale::cache_version());
+        meta.fetched_at -= chrono::TimeDelta::days(2);
+        (store.write(&key, &bytes, Sale::compression(), &meta)).unwrap();
+        let path = store.location(&key);
+        let policy = retention(
+            "Sale",
+            Retention {
+                max_age: Some(HOUR * 24),
+                max_count: None,
+            },
+        );
+
+        let report = delete(&store, &policy.clone().dry_run()).unwrap();
+        assert_eq!(reasons(&report.removed), [(key, RemovalReason::TooOld)]);
+        assert!(path.exists());
 
-        Ok(())
+        let report = delete(&store, &policy).unwrap();
+        assert_eq!(report.removed.len(), 1);
+        assert!(!path.exists());
+        std::fs::remove_dir_all(root).unwrap();
     }
 }
@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use futures::StreamExt;
+
+use crate::api::model::ranking::{self, Category};
+use crate::api::schema::{RankingAllMonthly, RankingSectionDaily, RankingSectionMonthly};
+use crate::app::api_loader::APILoader;
+use crate::app::api_loader::error::LoaderError;
+use crate::app::cache::Cacheable;
+
+/// 同時に取得する部門の数
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// 全部門のランキング
+#[derive(Debug)]
+pub struct Sections<T> {
+    /// 全部門の上位 ([RankingAllMonthly])
+    pub summary: ranking::AllMonthly,
+    /// 部門毎の結果, 失敗した部門は `Err`
+    pub sections: BTreeMap<Category, Result<T, LoaderError>>,
+}
+
+impl<T> Sections<T> {
+    pub fn categories(&self) -> impl Iterator<Item = &Category> {
+        self.sections.keys()
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = (&Category, &LoaderError)> {
+        (self.sections.iter())
+            .filter_map(|(category, result)| Some((category, result.as_ref().err()?)))
+    }
+}
+
+/// `loader` の月の全部門の月間ランキング
+///
+/// 部門は [RankingAllMonthly] から調べる
+pub async fn load_monthly_sections(
+    loader: &APILoader<RankingAllMonthly>,
+    concurrency: usize,
+) -> Result<Sections<ranking::SectionMonthly>, LoaderError> {
+    let ym = loader.schema().ym;
+    fan_out(loader, concurrency, |section| RankingSectionMonthly {
+        ym,
+        section,
+    })
+    .await
+}
+
+/// `date` の全部門の日間ランキング
+///
+/// 部門は `date` の月の [RankingAllMonthly] から調べる
+pub async fn load_daily_sections(
+    loader: &APILoader<RankingAllMonthly>,
+    date: NaiveDate,
+    concurrency: usize,
+) -> Result<Sections<ranking::Daily>, LoaderError> {
+    let loader = loader.with_schema(RankingAllMonthly { ym: date });
+    fan_out(&loader, concurrency, |section| RankingSectionDaily {
+        date,
+        section,
+    })
+    .await
+}
+
+async fn fan_out<S>(
+    loader: &APILoader<RankingAllMonthly>,
+    concurrency: usize,
+    schema: impl Fn(String) -> S,
+) -> Result<Sections<S::Response>, LoaderError>
+where
+    S: Cacheable + Clone + Send + Sync + 'static,
+    S::Response: Send,
+{
+    let summary = loader.get().await?;
+    let loaders = (summary.0.keys())
+        .map(|category| {
+            (
+                category.clone(),
+                loader.with_schema(schema(category.0.clone())),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let sections = futures::stream::iter(loaders)
+        .map(|(category, loader)| async move { (category, loader.get().await) })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+    Ok(Sections { summary, sections })
+}
@@ -0,0 +1,446 @@
+use std::any::Any;
+use std::fmt::{Debug, Display, Formatter};
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta, Timelike, Utc};
+use error::{ParamError, RegistryError};
+
+use crate::api::model::shop;
+use crate::api::schema::*;
+use crate::app::api_client::BoxFuture;
+use crate::app::api_loader::APILoader;
+use crate::app::cache::Cacheable;
+
+/// スキーマのパラメータの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Param {
+    Date,
+    Month,
+    Section,
+    Hour,
+    ShopId,
+}
+
+impl Param {
+    pub const ALL: [Param; 5] = [
+        Param::Date,
+        Param::Month,
+        Param::Section,
+        Param::Hour,
+        Param::ShopId,
+    ];
+
+    /// `name=value` の `name`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Param::Date => "date",
+            Param::Month => "month",
+            Param::Section => "section",
+            Param::Hour => "hour",
+            Param::ShopId => "shop_id",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Param::Date => "日付 (YYYY-MM-DD)",
+            Param::Month => "月 (YYYY-MM)",
+            Param::Section => "部門 (e.g. exp_62)",
+            Param::Hour => "時 (0-23)",
+            Param::ShopId => "店舗ID",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.name() == name)
+    }
+}
+
+impl Display for Param {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// 文字列から読んだパラメータ
+///
+/// 省略した日時は公開済みの最新のものになる
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Params {
+    pub date: Option<NaiveDate>,
+    /// 月の1日
+    pub month: Option<NaiveDate>,
+    pub section: Option<String>,
+    pub hour: Option<u8>,
+    pub shop_id: Option<shop::Id>,
+}
+
+impl Params {
+    pub fn set(&mut self, param: Param, value: &str) -> Result<&mut Self, ParamError> {
+        let invalid = || ParamError::Invalid {
+            param,
+            value: value.to_string(),
+        };
+        match param {
+            Param::Date => {
+                let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| invalid())?;
+                self.date = Some(date);
+            }
+            Param::Month => {
+                let month = NaiveDate::parse_from_str(&format!("{value}-01"), "%Y-%m-%d")
+                    .map_err(|_| invalid())?;
+                self.month = Some(month);
+            }
+            Param::Section if value.is_empty() => return Err(invalid()),
+            Param::Section => self.section = Some(value.to_string()),
+            Param::Hour => {
+                let hour = value.parse().ok().filter(|h| *h < 24).ok_or_else(invalid)?;
+                self.hour = Some(hour);
+            }
+            Param::ShopId => {
+                let shop_id = value
+                    .trim_start_matches('#')
+                    .parse()
+                    .map_err(|_| invalid())?;
+                self.shop_id = Some(shop::Id(shop_id));
+            }
+        }
+        Ok(self)
+    }
+
+    /// `name=value` の並び
+    pub fn parse<I>(args: I) -> Result<Self, ParamError>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut params = Self::default();
+        for arg in args {
+            let arg = arg.as_ref();
+            let (name, value) = arg
+                .split_once('=')
+                .ok_or_else(|| ParamError::Unknown(arg.to_string()))?;
+            let param =
+                Param::from_name(name).ok_or_else(|| ParamError::Unknown(name.to_string()))?;
+            params.set(param, value)?;
+        }
+        Ok(params)
+    }
+
+    fn section(&self) -> Result<String, ParamError> {
+        self.section
+            .clone()
+            .ok_or(ParamError::Missing(Param::Section))
+    }
+
+    fn date(&self, latest: impl FnOnce() -> NaiveDate) -> NaiveDate {
+        self.date.unwrap_or_else(latest)
+    }
+
+    fn month(&self) -> NaiveDate {
+        (self.month)
+            .or(self.date)
+            .unwrap_or_else(|| recent().date())
+    }
+}
+
+/// 公開済みと見なせる最新の時刻
+pub fn recent() -> NaiveDateTime {
+    server_time(Utc::now()) - TimeDelta::hours(1)
+}
+
+/// [REGISTRY] に載せるスキーマ
+pub trait Registered:
+    Cacheable<Response: Send + Sync + 'static> + Clone + Send + Sync + 'static
+{
+    /// 変えないこと (CLIの引数などで使う)
+    const KEY: &'static str;
+    const NAME_JA: &'static str;
+    const NAME_EN: &'static str;
+    const PARAMS: &'static [Param];
+
+    fn from_params(params: &Params) -> Result<Self, ParamError>;
+
+    /// 表示用の行
+    fn render(response: &Self::Response) -> Vec<String>;
+}
+
+fn display<T: Display>(items: impl IntoIterator<Item = T>) -> Vec<String> {
+    items.into_iter().map(|item| item.to_string()).collect()
+}
+
+fn debug<T: Debug>(items: impl IntoIterator<Item = T>) -> Vec<String> {
+    items.into_iter().map(|item| format!("{item:?}")).collect()
+}
+
+macro_rules! registry {
+    ( $(
+        $self:ident { $key:literal, $ja:literal, $en:literal, [$($param:ident),*] }
+        |$params:ident| $from:block
+        |$response:ident| $render:expr;
+    )+ ) => {
+        $(
+            impl Registered for $self {
+                const KEY: &'static str = $key;
+                const NAME_JA: &'static str = $ja;
+                const NAME_EN: &'static str = $en;
+                const PARAMS: &'static [Param] = &[$(Param::$param),*];
+
+                #[allow(unused_variables)]
+                fn from_params($params: &Params) -> Result<Self, ParamError> $from
+
+                fn render($response: &Self::Response) -> Vec<String> {
+                    $render
+                }
+            }
+        )+
+
+        /// 全てのスキーマ
+        pub static REGISTRY: LazyLock<Vec<SchemaInfo>> =
+            LazyLock::new(|| vec![$( SchemaInfo::of::<$self>(), )+]);
+    };
+}
+
+registry! {
+    OfficialItem { "official_item", "商品定義", "item(official)", [] }
+        |params| { Ok(OfficialItem) }
+        |response| display(response.0.values());
+    RecipeItem { "recipe_item", "レシピ商品定義", "item(recipe)", [] }
+        |params| { Ok(RecipeItem) }
+        |response| display(response.0.values());
+    Area { "area", "街定義", "area", [] }
+        |params| { Ok(Area) }
+        |response| debug(response.values());
+    Report { "report", "レポート", "report", [Date] }
+        |params| { Ok(Report::new(params.date(|| recent().date().pred_opt().unwrap()))?) }
+        |response| debug([response]);
+    RankingAllMonthly { "ranking_all_monthly", "ランキング/月間全部門トップ3", "ranking(all)", [Month] }
+        |params| { Ok(RankingAllMonthly::new(params.month())?) }
+        |response| debug(&response.0);
+    RankingSectionMonthly { "ranking_section_monthly", "ランキング/月間部門別", "ranking(section)", [Month, Section] }
+        |params| { Ok(RankingSectionMonthly::new(params.month(), params.section()?)?) }
+        |response| debug(&response.0);
+    RankingSectionDaily { "ranking_section_daily", "ランキング/日間部門別", "ranking(daily)", [Date, Section] }
+        |params| { Ok(RankingSectionDaily::new(params.date(|| recent().date()), params.section()?)?) }
+        |response| debug(&response.0);
+    Sale { "sale", "販売中商品", "sale", [] }
+        |params| { Ok(Sale) }
+        |response| debug(response);
+    Request { "request", "注文中商品", "request", [] }
+        |params| { Ok(Request) }
+        |response| debug(response);
+    ShopSummary { "shop_summary", "お店情報", "shop summary", [] }
+        |params| { Ok(ShopSummary) }
+        |response| display([response]);
+    Shop { "shop", "全お店リスト", "shop", [] }
+        |params| { Ok(Shop) }
+        |response| debug(response);
+    People { "people", "住民", "people", [] }
+        |params| { Ok(People) }
+        |response| display(&response.0);
+    RequestReport { "request_report", "注文レポート", "request report", [Date, Hour, ShopId] }
+        |params| {
+            // 店舗IDがあれば店舗別, 無ければ時間帯別
+            let recent = recent();
+            let date = params.date(|| recent.date());
+            Ok(match params.shop_id {
+                Some(shop_id) => RequestReport::shop(date, shop_id)?,
+                None => RequestReport::all(date, params.hour.unwrap_or(recent.hour() as u8))?,
+            })
+        }
+        |response| display(&response.0);
+    AreaSummary { "area_summary", "街情報", "area summary", [] }
+        |params| { Ok(AreaSummary) }
+        |response| display(&response.0);
+}
+
+/// 登録されたスキーマの情報
+#[derive(Debug)]
+pub struct SchemaInfo {
+    pub key: &'static str,
+    pub name_ja: &'static str,
+    pub name_en: &'static str,
+    pub params: &'static [Param],
+    pub min_interval: Duration,
+    /// [Cacheable::file_dir]
+    pub cache_dir: Option<PathBuf>,
+    load: fn(Params, PathBuf) -> BoxFuture<'static, Result<DynResponse, RegistryError>>,
+}
+
+impl SchemaInfo {
+    fn of<S: Registered>() -> Self {
+        Self {
+            key: S::KEY,
+            name_ja: S::NAME_JA,
+            name_en: S::NAME_EN,
+            params: S::PARAMS,
+            min_interval: S::min_interval(),
+            cache_dir: S::file_dir().map(|dir| dir.as_ref().to_path_buf()),
+            load: load::<S>,
+        }
+    }
+
+    /// `params` からスキーマを作り, `cache_root` のキャッシュを使って読み込む
+    pub fn load(
+        &self,
+        params: Params,
+        cache_root: PathBuf,
+    ) -> BoxFuture<'static, Result<DynResponse, RegistryError>> {
+        (self.load)(params, cache_root)
+    }
+}
+
+impl Display for SchemaInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} ({})", self.key, self.name_ja, self.name_en)?;
+        for param in self.params {
+            write!(f, " [{}={}]", param, param.description())?;
+        }
+        Ok(())
+    }
+}
+
+pub fn find(key: &str) -> Option<&'static SchemaInfo> {
+    REGISTRY.iter().find(|info| info.key == key)
+}
+
+fn load<S: Registered>(
+    params: Params,
+    cache_root: PathBuf,
+) -> BoxFuture<'static, Result<DynResponse, RegistryError>> {
+    Box::pin(async move {
+        let mut loader = APILoader::new(S::from_params(&params)?);
+        loader.set_cache_root(cache_root);
+        let value = loader.get_shared().await?;
+        Ok(DynResponse {
+            value,
+            render: render::<S>,
+        })
+    })
+}
+
+fn render<S: Registered>(value: &dyn Any) -> Vec<String> {
+    value
+        .downcast_ref::<S::Response>()
+        .map(S::render)
+        .unwrap_or_default()
+}
+
+/// 型を消した読み込み結果
+#[derive(Clone)]
+pub struct DynResponse {
+    value: Arc<dyn Any + Send + Sync>,
+    render: fn(&dyn Any) -> Vec<String>,
+}
+
+impl DynResponse {
+    /// [Schema::Response] に戻す
+    pub fn downcast<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        Arc::clone(&self.value).downcast().ok()
+    }
+
+    /// 表示用の行
+    pub fn lines(&self) -> Vec<String> {
+        (self.render)(&*self.value)
+    }
+}
+
+impl Debug for DynResponse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DynResponse").finish_non_exhaustive()
+    }
+}
+
+pub mod error {
+    use std::error::Error;
+    use std::fmt::{Display, Formatter, Result};
+
+    use super::Param;
+    use crate::api::schema::error::InvalidParameter;
+    use crate::app::api_loader::error::LoaderError;
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum ParamError {
+        /// 知らないパラメータ, または `name=value` の形でない
+        Unknown(String),
+        Missing(Param),
+        Invalid {
+            param: Param,
+            value: String,
+        },
+        /// 読めたが取得できない値 (未公開など)
+        Rejected(InvalidParameter),
+    }
+
+    impl From<InvalidParameter> for ParamError {
+        fn from(e: InvalidParameter) -> Self {
+            ParamError::Rejected(e)
+        }
+    }
+
+    impl Display for ParamError {
+        fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+            match self {
+                ParamError::Unknown(arg) => write!(f, "Unknown parameter: {}", arg),
+                ParamError::Missing(param) => {
+                    write!(f, "Missing parameter: {} ({})", param, param.description())
+                }
+                ParamError::Invalid { param, value } => write!(
+                    f,
+                    "Invalid parameter: {}={} ({})",
+                    param,
+                    value,
+                    param.description()
+                ),
+                ParamError::Rejected(e) => write!(f, "Invalid parameter: {}", e),
+            }
+        }
+    }
+
+    impl Error for ParamError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            match self {
+                ParamError::Rejected(e) => Some(e),
+                _ => None,
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    pub enum RegistryError {
+        Param(ParamError),
+        Load(LoaderError),
+    }
+
+    impl From<ParamError> for RegistryError {
+        fn from(e: ParamError) -> Self {
+            RegistryError::Param(e)
+        }
+    }
+
+    impl From<LoaderError> for RegistryError {
+        fn from(e: LoaderError) -> Self {
+            RegistryError::Load(e)
+        }
+    }
+
+    impl Display for RegistryError {
+        fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+            match self {
+                RegistryError::Param(e) => write!(f, "{}", e),
+                RegistryError::Load(e) => write!(f, "{}", e),
+            }
+        }
+    }
+
+    impl Error for RegistryError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            match self {
+                RegistryError::Param(e) => Some(e),
+                RegistryError::Load(e) => Some(e),
+            }
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Timelike};
+use futures::StreamExt;
+
+use crate::api::model::request_report::RequestReport as Trade;
+use crate::api::model::trade::{Gold, Price, Units};
+use crate::api::model::{item, shop};
+use crate::api::schema::RequestReport;
+use crate::app::api_loader::APILoader;
+use crate::app::api_loader::error::LoaderError;
+
+/// 同時に取得する時間帯の数
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// 時間帯毎の [RequestReport::All] をまとめた注文の取引
+#[derive(Debug, Default)]
+pub struct RequestTrades {
+    /// 時間帯を跨いだ重複を除き, 取引時刻順
+    pub trades: Vec<Trade>,
+    /// 取得できなかった時間帯
+    pub missing: Vec<(NaiveDate, u8, LoaderError)>,
+}
+
+/// 取引の合計
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Total {
+    /// 取引の件数
+    pub trades: usize,
+    /// 個数の合計
+    pub count: Units,
+    /// 金額 (個数 × 単価) の合計
+    pub gold: Gold,
+}
+
+impl Total {
+    fn add(&mut self, trade: &Trade) {
+        self.trades += 1;
+        self.count += trade.item_count;
+        self.gold += trade.order_price * trade.item_count;
+    }
+}
+
+impl Display for Total {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} trades, x{}, {}", self.trades, self.count, self.gold)
+    }
+}
+
+/// `date` の0時から23時まで
+pub async fn load_day(
+    loader: &APILoader<RequestReport>,
+    date: NaiveDate,
+    concurrency: usize,
+) -> RequestTrades {
+    let start = date.and_time(NaiveTime::MIN);
+    load_range(loader, start, start + TimeDelta::days(1), concurrency).await
+}
+
+/// `start` を含む時間帯から `end` の前の時間帯まで
+///
+/// 未公開などで取得できない時間帯は [LoaderError::InvalidParameters] として `missing` に入る
+///
+/// `loader` の設定 (接続先, 保存先など) で取得し, スキーマは時間帯毎に置き換える
+pub async fn load_range(
+    loader: &APILoader<RequestReport>,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    concurrency: usize,
+) -> RequestTrades {
+    // 検証は取得時に行う
+    let results = futures::stream::iter(hours(start, end))
+        .map(|(date, hour)| async move {
+            let schema = RequestReport::All { date, hour };
+            (date, hour, loader.with_schema(schema).get().await)
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut trades = RequestTrades::default();
+    let mut batches = Vec::new();
+    for (date, hour, result) in results {
+        match result {
+            Ok(response) => batches.push(response.0),
+            Err(e) => trades.missing.push((date, hour, e)),
+        }
+    }
+    trades.trades = merge(batches);
+    trades.normalize();
+    trades
+}
+
+/// 取引の内容 (同じ秒, 店, アイテム, 数量, 単価の取引は区別できない)
+type TradeKey = (i64, shop::Id, shop::Id, item::Id, Units, Price);
+
+fn trade_key(t: &Trade) -> TradeKey {
+    (
+        t.timestamp,
+        t.seller_shop_id,
+        t.buyer_shop_id,
+        t.item_id.clone(),
+        t.item_count,
+        t.order_price,
+    )
+}
+
+/// 時間帯毎の取得結果をまとめる
+///
+/// 時間帯の境目の取引は隣の時間帯にも載ることがあるので, 取得結果を跨いだ重複だけを除く.
+/// 1つの取得結果の中で内容が同じ取引は別々の取引として数える
+fn merge(batches: impl IntoIterator<Item = Vec<Trade>>) -> Vec<Trade> {
+    let mut merged = Vec::new();
+    // 内容毎に, これまでのどれか1つの取得結果に含まれていた件数の最大
+    let mut kept = HashMap::<TradeKey, usize>::new();
+    for batch in batches {
+        let mut counts = HashMap::<TradeKey, usize>::new();
+        for t in batch {
+            let key = trade_key(&t);
+            let count = counts.entry(key.clone()).or_default();
+            *count += 1;
+            let max = kept.entry(key).or_default();
+            if *max < *count {
+                *max += 1;
+                merged.push(t);
+            }
+        }
+    }
+    merged
+}
+
+/// `[start, end)` に掛かる時間帯
+fn hours(start: NaiveDateTime, end: NaiveDateTime) -> impl Iterator<Item = (NaiveDate, u8)> {
+    let first = start.date().and_hms_opt(start.hour(), 0, 0);
+    std::iter::successors(first, |t| t.checked_add_signed(TimeDelta::hours(1)))
+        .take_while(move |t| *t < end)
+        .map(|t| (t.date(), t.hour() as u8))
+}
+
+impl RequestTrades {
+    /// 取引時刻順に並べる
+    fn normalize(&mut self) {
+        self.trades.sort_by_key(|t| t.timestamp);
+        self.missing.sort_by_key(|(date, hour, _)| (*date, *hour));
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    pub fn total(&self) -> Total {
+        let mut total = Total::default();
+        self.trades.iter().for_each(|t| total.add(t));
+        total
+    }
+
+    pub fn by_item(&self) -> BTreeMap<item::Id, Total> {
+        self.totals_by(|t| t.item_id.clone())
+    }
+
+    pub fn by_seller(&self) -> BTreeMap<shop::Id, Total> {
+        self.totals_by(|t| t.seller_shop_id)
+    }
+
+    pub fn by_buyer(&self) -> BTreeMap<shop::Id, Total> {
+        self.totals_by(|t| t.buyer_shop_id)
+    }
+
+    /// 店名 (期間中で最後のもの)
+    pub fn shop_names(&self) -> BTreeMap<shop::Id, &shop::Name> {
+        let mut names = BTreeMap::new();
+        for t in &self.trades {
+            names.insert(t.seller_shop_id, &t.seller_shop_name);
+            names.insert(t.buyer_shop_id, &t.buyer_shop_name);
+        }
+        names
+    }
+
+    fn totals_by<K: Ord>(&self, key: impl Fn(&Trade) -> K) -> BTreeMap<K, Total> {
+        let mut totals = BTreeMap::<K, Total>::new();
+        for t in &self.trades {
+            totals.entry(key(t)).or_default().add(t);
+        }
+        totals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(seller: u32, timestamp: i64) -> Trade {
+        let json = format!(r#"[{seller},"seller",2,"buyer",5,3,100,{timestamp}]"#);
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn merge_removes_duplicates_across_batches_only() {
+        let first = vec![trade(1, 100), trade(1, 100), trade(3, 200)];
+        let second = vec![trade(1, 100), trade(3, 200), trade(4, 300)];
+        let merged = merge([first, second]);
+
+        let count = |seller| {
+            merged
+                .iter()
+                .filter(|t| t.seller_shop_id.0 == seller)
+                .count()
+        };
+        // 同じ時間帯に同じ内容の取引が2件あれば2件のまま
+        assert_eq!(count(1), 2);
+        assert_eq!(count(3), 1);
+        assert_eq!(count(4), 1);
+        assert_eq!(merged.len(), 4);
+    }
+
+    #[test]
+    fn merge_keeps_the_larger_count() {
+        let first = vec![trade(1, 100)];
+        let second = vec![trade(1, 100), trade(1, 100), trade(1, 100)];
+        assert_eq!(merge([first.clone(), second.clone()]).len(), 3);
+        assert_eq!(merge([second, first]).len(), 3);
+    }
+}
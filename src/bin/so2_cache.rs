@@ -0,0 +1,95 @@
+//! キャッシュの確認用CLI
+//!
+//! `cargo run --bin so2_cache -- <command> [--root <dir>] [--dry-run] [--budget <MiB>]`
+//!
+//! - `list`: キャッシュの一覧
+//! - `health`: 壊れていたデータの集計
+//! - `clean`: 期限切れのキャッシュの削除
+//! - `schemas`: スキーマの一覧
+//! - `load <key> [<name>=<value>...]`: スキーマを読み込んで表示 (e.g. `load report date=2024-01-01`)
+
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use so2_tool::app::cache::DEFAULT_CACHE_ROOT;
+use so2_tool::app::cache::index::CacheIndex;
+use so2_tool::app::cache::quarantine::Quarantine;
+use so2_tool::app::cache::store;
+use so2_tool::app::delete_expired_cache::{self, CleanupPolicy};
+use so2_tool::app::registry::{self, Params, REGISTRY};
+
+const USAGE: &str = "usage: so2_cache [list|health|clean|schemas|load <key> [<name>=<value>...]] \
+    [--root <dir>] [--dry-run] [--budget <MiB>]";
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let command = args.next().unwrap_or_else(|| "list".to_string());
+
+    let mut root = DEFAULT_CACHE_ROOT.clone();
+    let mut policy = CleanupPolicy::default();
+    let mut operands = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dry-run" => policy.dry_run = true,
+            "--root" => match args.next() {
+                Some(dir) => root = PathBuf::from(dir),
+                None => return usage(),
+            },
+            "--budget" => match args.next().and_then(|mib| mib.parse::<u64>().ok()) {
+                Some(mib) => policy.size_budget = Some(mib * 1024 * 1024),
+                None => return usage(),
+            },
+            _ if command == "load" && !arg.starts_with("--") => operands.push(arg),
+            _ => return usage(),
+        }
+    }
+
+    let result: Result<String, Box<dyn Error>> = match command.as_str() {
+        "list" => CacheIndex::scan(&*store::open(&root))
+            .map(|index| index.to_string())
+            .map_err(Into::into),
+        "health" => (Quarantine::new(&root).health())
+            .map(|h| h.to_string())
+            .map_err(Into::into),
+        "clean" => delete_expired_cache::delete(&*store::open(&root), &policy)
+            .map(|r| r.to_string())
+            .map_err(Into::into),
+        "schemas" => Ok(REGISTRY
+            .iter()
+            .map(|info| info.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")),
+        "load" => match operands.split_first() {
+            Some((key, args)) => load(key, args, root),
+            None => return usage(),
+        },
+        _ => return usage(),
+    };
+
+    match result {
+        Ok(output) => {
+            println!("{output}");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn usage() -> ExitCode {
+    eprintln!("{USAGE}");
+    ExitCode::FAILURE
+}
+
+fn load(key: &str, args: &[String], root: PathBuf) -> Result<String, Box<dyn Error>> {
+    let info = registry::find(key).ok_or_else(|| format!("unknown schema: {key}"))?;
+    let params = Params::parse(args)?;
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let response = runtime.block_on(info.load(params, root))?;
+    Ok(response.lines().join("\n"))
+}
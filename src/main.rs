@@ -1,20 +1,20 @@
-use std::error::Error;
 use std::fmt::{Debug, Display};
 
-use chrono::Timelike;
 use iced::alignment::Vertical;
 use iced::widget::text::Shaping;
-use iced::widget::{Row, button, column, container, pick_list, row, scrollable, text};
+use iced::widget::{Column, Row, button, column, container, pick_list, row, scrollable, text};
 use iced::{Element, Length, Task, Theme};
 use itertools::Itertools;
-use so2_tool::api::schema::{
-    Area, AreaSummary, OfficialItem, People, RankingAllMonthly, RankingSectionDaily,
-    RankingSectionMonthly, RecipeItem, Report, Request, RequestReport, Sale, Schema, Shop,
-    ShopSummary,
-};
+use so2_tool::api::schema::{RankingAllMonthly, RankingSectionDaily};
 use so2_tool::app::api_loader::APILoader;
 use so2_tool::app::cache::DEFAULT_CACHE_ROOT;
-use so2_tool::app::delete_expired_cache;
+use so2_tool::app::cache::index::CacheIndex;
+use so2_tool::app::cache::memory::MEMORY;
+use so2_tool::app::cache::quarantine::Quarantine;
+use so2_tool::app::cache::store;
+use so2_tool::app::delete_expired_cache::{self, CleanupPolicy};
+use so2_tool::app::ranking_loader::{self, Sections};
+use so2_tool::app::registry::{self, Param, Params, Registered, SchemaInfo};
 
 pub fn main() -> iced::Result {
     iced::application(
@@ -40,140 +40,66 @@ impl Default for ItemsLabel {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-enum LoadTarget {
-    OfficialItem,
-    RecipeItem,
-    Area,
-    Report,
-    Ranking(Ranking),
-    Sale,
-    Request,
-    ShopSummary,
-    Shop,
-    People,
-    RequestReport,
-    AreaSummary,
-}
-
-#[derive(Debug, Clone, Copy)]
-enum Ranking {
-    All,
-    Section,
-    Daily,
-}
-
 #[derive(Debug, Clone)]
 enum Message {
     ThemeChanged(Theme),
-    Load(LoadTarget),
+    Load(&'static SchemaInfo),
+    LoadSections(&'static SchemaInfo),
     Loaded(String),
     DeleteCache,
+    ShowCache,
 }
 
 impl ItemsLabel {
-    fn to_display<Iter>(v: Result<Iter, Box<dyn Error>>) -> String
+    fn to_display<Iter, E>(v: Result<Iter, E>) -> String
     where
         Iter: IntoIterator,
         Iter::Item: Display,
+        E: Display,
     {
         v.inspect_err(|e| eprintln!("{e}"))
             .map_or_else(|e| format!("error: {e}"), |v| v.into_iter().join("\n"))
     }
 
-    fn to_debug<Iter>(v: Result<Iter, Box<dyn Error>>) -> String
+    fn sections_to_display<T, E>(v: Result<Sections<T>, E>) -> String
     where
-        Iter: IntoIterator,
-        Iter::Item: Debug,
+        T: Debug,
+        E: Display,
     {
-        Self::to_display(v.map(|v| v.into_iter().map(|v| format!("{v:?}"))))
+        Self::to_display(v.map(|v| {
+            (v.sections.into_iter()).map(|(category, section)| match section {
+                Ok(section) => format!("{}: {:?}", category.0, section),
+                Err(e) => format!("{}: error: {e}", category.0),
+            })
+        }))
     }
 
     fn update(&mut self, message: Message) -> Task<Message> {
         match message {
-            Message::Load(target) => Task::perform(
+            Message::Load(info) => Task::perform(
                 async move {
-                    match target {
-                        LoadTarget::OfficialItem => Self::to_display(
-                            APILoader::new(OfficialItem)
-                                .get()
-                                .await
-                                .map(|v| v.0.into_values()),
-                        ),
-                        LoadTarget::RecipeItem => Self::to_display(
-                            APILoader::new(RecipeItem)
-                                .get()
-                                .await
-                                .map(|v| v.0.into_values()),
-                        ),
-                        LoadTarget::Area => Self::to_debug(
-                            APILoader::new(Area).get().await.map(|v| v.into_values()),
-                        ),
-                        LoadTarget::Report => {
-                            let instant = chrono::Local::now() - RankingAllMonthly::min_interval();
-                            Self::to_debug(
-                                APILoader::new(Report(instant.date_naive().pred_opt().unwrap()))
-                                    .get()
-                                    .await
-                                    .map(|v| [v]),
-                            )
-                        }
-                        LoadTarget::Ranking(r) => {
-                            let instant = chrono::Local::now() - RankingAllMonthly::min_interval();
-
-                            match r {
-                                Ranking::All => Self::to_debug(
-                                    APILoader::new(RankingAllMonthly {
-                                        ym: instant.date_naive(),
-                                    })
-                                    .get()
-                                    .await
-                                    .map(|v| v.0),
-                                ),
-                                Ranking::Section => Self::to_debug(
-                                    APILoader::new(RankingSectionMonthly {
-                                        ym: instant.date_naive(),
-                                        section: "exp_62".to_string(),
-                                    })
-                                    .get()
-                                    .await
-                                    .map(|v| v.0),
-                                ),
-                                Ranking::Daily => Self::to_debug(
-                                    APILoader::new(RankingSectionDaily {
-                                        date: instant.date_naive(),
-                                        section: "exp_62".to_string(),
-                                    })
-                                    .get()
-                                    .await
-                                    .map(|v| v.0),
-                                ),
-                            }
-                        }
-                        LoadTarget::Sale => Self::to_debug(APILoader::new(Sale).get().await),
-                        LoadTarget::Request => Self::to_debug(APILoader::new(Request).get().await),
-                        LoadTarget::ShopSummary => {
-                            Self::to_display(APILoader::new(ShopSummary).get().await.map(|v| [v]))
-                        }
-                        LoadTarget::Shop => Self::to_debug(APILoader::new(Shop).get().await),
-                        LoadTarget::People => {
-                            Self::to_display(APILoader::new(People).get().await.map(|v| v.0))
-                        }
-
-                        LoadTarget::RequestReport => {
-                            let instant = chrono::Local::now() - RequestReport::min_interval();
-                            let date = instant.date_naive();
-                            let hour = instant.hour() as u8;
-                            Self::to_display(
-                                APILoader::new(RequestReport::All { date, hour })
-                                    .get()
-                                    .await
-                                    .map(|v| v.0),
-                            )
-                        }
-                        LoadTarget::AreaSummary => {
-                            Self::to_display(APILoader::new(AreaSummary).get().await.map(|v| v.0))
-                        }
+                    let loaded = info
+                        .load(Params::default(), DEFAULT_CACHE_ROOT.clone())
+                        .await;
+                    Self::to_display(loaded.map(|v| v.lines()))
+                },
+                Message::Loaded,
+            ),
+            Message::LoadSections(info) => Task::perform(
+                async move {
+                    let month = APILoader::new(RankingAllMonthly {
+                        ym: registry::recent().date(),
+                    });
+                    let concurrency = ranking_loader::DEFAULT_CONCURRENCY;
+                    if info.key == RankingSectionDaily::KEY {
+                        let date = registry::recent().date();
+                        Self::sections_to_display(
+                            ranking_loader::load_daily_sections(&month, date, concurrency).await,
+                        )
+                    } else {
+                        Self::sections_to_display(
+                            ranking_loader::load_monthly_sections(&month, concurrency).await,
+                        )
                     }
                 },
                 Message::Loaded,
@@ -188,18 +114,30 @@ impl ItemsLabel {
                 Task::none()
             }
             Message::DeleteCache => {
-                delete_expired_cache::delete(&DEFAULT_CACHE_ROOT).unwrap();
+                MEMORY.clear();
+                let policy = CleanupPolicy::default();
+                self.display =
+                    delete_expired_cache::delete(&*store::open(&DEFAULT_CACHE_ROOT), &policy)
+                        .map_or_else(|e| format!("error: {e}"), |report| report.to_string());
+                Task::none()
+            }
+            Message::ShowCache => {
+                let index = CacheIndex::scan(&*store::open(&DEFAULT_CACHE_ROOT));
+                let health = Quarantine::new(&DEFAULT_CACHE_ROOT).health();
+                self.display = match (index, health) {
+                    (Ok(index), Ok(health)) => format!("{index}\n\n{health}"),
+                    (Err(e), _) | (_, Err(e)) => format!("error: {e}"),
+                };
                 Task::none()
             }
         }
     }
 
     fn view(&self) -> Element<Message> {
-        let load_button = |label, target| button(label).on_press(Message::Load(target));
-
         column![
             container(
                 row![
+                    button("cache").on_press(Message::ShowCache),
                     button("delete cache").on_press(Message::DeleteCache),
                     container(self.theme_selector_view())
                 ]
@@ -209,22 +147,15 @@ impl ItemsLabel {
             .align_right(Length::Fill)
             .width(Length::Fill),
             row![
-                column![
-                    load_button("item(official)", LoadTarget::OfficialItem),
-                    load_button("item(recipe)", LoadTarget::RecipeItem),
-                    load_button("area", LoadTarget::Area),
-                    load_button("report", LoadTarget::Report),
-                    load_button("ranking(all)", LoadTarget::Ranking(Ranking::All)),
-                    load_button("ranking(section)", LoadTarget::Ranking(Ranking::Section)),
-                    load_button("ranking(daily)", LoadTarget::Ranking(Ranking::Daily)),
-                    load_button("sale", LoadTarget::Sale),
-                    load_button("request", LoadTarget::Request),
-                    load_button("shop summary", LoadTarget::ShopSummary),
-                    load_button("shop", LoadTarget::Shop),
-                    load_button("people", LoadTarget::People),
-                    load_button("request report", LoadTarget::RequestReport),
-                    load_button("area summary", LoadTarget::AreaSummary),
-                ]
+                Column::with_children(registry::REGISTRY.iter().map(|info| {
+                    // 部門の指定が要るものは全部門をまとめて読む
+                    let message = if info.params.contains(&Param::Section) {
+                        Message::LoadSections(info)
+                    } else {
+                        Message::Load(info)
+                    };
+                    button(info.name_en).on_press(message).into()
+                }))
                 .spacing(5),
                 self.scrollable_text_view(&self.display),
             ]
@@ -0,0 +1,92 @@
+//! 未公開や範囲外のものは送らずに結果にする
+
+mod common;
+
+use std::time::Duration;
+
+use chrono::{Days, Utc};
+use so2_tool::api::model::ranking::EPOCH;
+use so2_tool::api::schema::{Report, server_time};
+use so2_tool::app::api_client::{ApiClient, LocalDirFetch, RetryPolicy};
+use so2_tool::app::api_loader::error::LoaderError;
+use so2_tool::app::backfill::{BackfillOptions, DateRange, Outcome, backfill};
+
+use common::{block_on, fixture, temp_dir};
+
+#[test]
+fn unpublished_days_are_not_fetched() {
+    let root = temp_dir("backfill_unpublished");
+    let today = server_time(Utc::now()).date();
+    let published = today - Days::new(2);
+    let path = published.format("json/report/buy%Y%m%d.json").to_string();
+    let server = root.join("server");
+    std::fs::create_dir_all(server.join("json/report")).unwrap();
+    std::fs::write(server.join(path), fixture("report.json")).unwrap();
+
+    let origin = "https://so2-api.mutoys.com/".parse().unwrap();
+    let client =
+        ApiClient::new(origin, LocalDirFetch { root: server }).with_retry(RetryPolicy::NONE);
+    let options = BackfillOptions {
+        delay: Duration::ZERO,
+        client,
+        cache_root: root.join("cache"),
+        ..Default::default()
+    };
+    let before_epoch = Report(EPOCH.pred_opt().unwrap());
+    let schemas = DateRange::new(published, today).days().map(Report);
+
+    let mut outcomes = Vec::new();
+    let report = block_on(backfill(
+        schemas.chain([before_epoch]),
+        &options,
+        |progress| outcomes.push((progress.schema.0, format!("{}", progress))),
+    ));
+
+    assert_eq!(report.fetched, 1);
+    // 今日の分は公開前, 昨日の分は1時までは公開前
+    assert!((1..=2).contains(&report.unpublished));
+    assert_eq!(report.fetched + report.unpublished + report.failed.len(), 4);
+    for (schema, e) in &report.failed {
+        match e {
+            Some(LoaderError::InvalidParameters { .. }) if schema.0 < EPOCH => {}
+            // 1時を過ぎていれば昨日の分はサーバーに無い
+            Some(LoaderError::HttpStatus { .. }) if schema.0 == today - Days::new(1) => {}
+            other => panic!("{:?}: unexpected {other:?}", schema.0),
+        }
+    }
+    assert!(
+        outcomes
+            .iter()
+            .any(|(date, line)| *date == today && line.contains("unpublished"))
+    );
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn outcome_is_reported_per_item() {
+    // 公開前のものは [Outcome::Unpublished] として通知される
+    let root = temp_dir("backfill_outcome");
+    let origin = "https://so2-api.mutoys.com/".parse().unwrap();
+    let options = BackfillOptions {
+        delay: Duration::ZERO,
+        client: ApiClient::new(
+            origin,
+            LocalDirFetch {
+                root: root.join("server"),
+            },
+        ),
+        cache_root: root.join("cache"),
+        ..Default::default()
+    };
+    let today = server_time(Utc::now()).date();
+    let mut unpublished = 0;
+    let report = block_on(backfill([Report(today)], &options, |progress| {
+        if matches!(progress.outcome, Outcome::Unpublished(_)) {
+            unpublished += 1;
+        }
+    }));
+    assert_eq!(unpublished, 1);
+    assert_eq!(report.unpublished, 1);
+    assert!(report.failed.is_empty());
+    std::fs::remove_dir_all(root).unwrap();
+}
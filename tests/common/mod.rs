@@ -0,0 +1,94 @@
+//! 結合テストで共有する準備
+
+// 結合テスト毎に使う関数が異なる
+#![allow(dead_code)]
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use reqwest::StatusCode;
+use so2_tool::app::api_client::error::TransportError;
+use so2_tool::app::api_client::{BoxFuture, Fetch, FetchRequest, FetchResponse, ResponseHeaders};
+
+/// テスト毎の空の一時ディレクトリ
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("so2_tool_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+pub fn fixture(name: &str) -> Vec<u8> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    std::fs::read(&path).unwrap_or_else(|e| panic!("{}: {e}", path.display()))
+}
+
+/// `dir` 以下のファイル (ロックファイルを除く)
+pub fn files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files.extend(self::files(&path));
+        } else if path.extension().is_none_or(|ext| ext != "lock") {
+            files.push(path);
+        }
+    }
+    files
+}
+
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+type Script = VecDeque<(StatusCode, Vec<u8>)>;
+
+/// 決めた順に応答を返す, 尽きたら 500
+#[derive(Clone, Default)]
+pub struct Scripted {
+    responses: Arc<Mutex<Script>>,
+    calls: Arc<AtomicUsize>,
+}
+
+impl Scripted {
+    pub fn new(responses: impl IntoIterator<Item = (StatusCode, Vec<u8>)>) -> Self {
+        Self {
+            responses: Arc::new(Mutex::new(responses.into_iter().collect())),
+            calls: Arc::default(),
+        }
+    }
+
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+impl Fetch for Scripted {
+    fn fetch<'a>(
+        &'a self,
+        _request: &'a FetchRequest,
+    ) -> BoxFuture<'a, Result<FetchResponse, TransportError>> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let (status, body) = (self.responses.lock().unwrap().pop_front())
+            .unwrap_or((StatusCode::INTERNAL_SERVER_ERROR, Vec::new()));
+        Box::pin(async move {
+            Ok(FetchResponse {
+                status,
+                headers: ResponseHeaders::default(),
+                body,
+            })
+        })
+    }
+}
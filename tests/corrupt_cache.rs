@@ -0,0 +1,94 @@
+//! 展開できないキャッシュは隔離して取り直す
+
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use futures::StreamExt;
+use so2_tool::api::schema::Sale;
+use so2_tool::app::api_client::{ApiClient, LocalDirFetch, RetryPolicy};
+use so2_tool::app::api_loader::APILoader;
+use so2_tool::app::api_loader::error::LoaderError;
+
+use common::{block_on, files, fixture, temp_dir};
+
+fn loader(root: &Path) -> APILoader<Sale> {
+    let server = root.join("server");
+    std::fs::create_dir_all(server.join("json/sale")).unwrap();
+    std::fs::write(server.join("json/sale/all.json"), fixture("sale.json")).unwrap();
+
+    let origin = "https://so2-api.mutoys.com/".parse().unwrap();
+    let client =
+        ApiClient::new(origin, LocalDirFetch { root: server }).with_retry(RetryPolicy::NONE);
+    let mut loader = APILoader::with_client(Sale, client);
+    loader.set_cache_root(root.join("cache"));
+    loader
+}
+
+/// 保存済みの `.gz` を途中で切る
+fn truncate_gzip(cache: &Path) -> PathBuf {
+    let gz = files(cache)
+        .into_iter()
+        .find(|path| path.extension().is_some_and(|ext| ext == "gz"))
+        .expect("gzip cache");
+    let bytes = std::fs::read(&gz).unwrap();
+    std::fs::write(&gz, &bytes[..bytes.len() / 2]).unwrap();
+    gz
+}
+
+fn quarantined(cache: &Path) -> usize {
+    let corrupt = files(&cache.join("quarantine")).into_iter();
+    corrupt
+        .filter(|path| path.extension().is_some_and(|ext| ext == "corrupt"))
+        .count()
+}
+
+#[test]
+fn truncated_gzip_is_refetched() {
+    let root = temp_dir("corrupt_cache_get");
+    let loader = loader(&root);
+    block_on(loader.get()).unwrap();
+    truncate_gzip(&loader.cache_root);
+
+    // 期限内のキャッシュでも読めなければ取り直す
+    let sales = block_on(loader.get()).unwrap();
+    assert_eq!(sales.len(), 2);
+    assert_eq!(quarantined(&loader.cache_root), 1);
+    assert_eq!(loader.load_cache().unwrap().len(), 2);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn truncated_gzip_is_reported_by_load_cache() {
+    let root = temp_dir("corrupt_cache_load");
+    let loader = loader(&root);
+    block_on(loader.get()).unwrap();
+    truncate_gzip(&loader.cache_root);
+
+    match loader.load_cache() {
+        Err(so2_tool::app::api_loader::error::CacheLoadError::ParseFailed { .. }) => {}
+        other => panic!("unexpected: {other:?}"),
+    }
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn truncated_gzip_stream_is_quarantined() {
+    let root = temp_dir("corrupt_cache_stream");
+    let loader = loader(&root);
+    block_on(loader.get()).unwrap();
+    truncate_gzip(&loader.cache_root);
+
+    let items = block_on(async { loader.stream().await.unwrap().collect::<Vec<_>>().await });
+    assert!(
+        items
+            .iter()
+            .any(|item| matches!(item, Err(LoaderError::CacheCorrupt { .. })))
+    );
+    assert_eq!(quarantined(&loader.cache_root), 1);
+
+    // 次は取り直す
+    let items = block_on(async { loader.stream().await.unwrap().collect::<Vec<_>>().await });
+    assert_eq!(items.into_iter().filter(Result::is_ok).count(), 2);
+    std::fs::remove_dir_all(root).unwrap();
+}
@@ -0,0 +1,255 @@
+//! [FetchPolicy] 毎のキャッシュと通信の使い分け
+
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use so2_tool::api::schema::Sale;
+use so2_tool::app::api_client::{ApiClient, RetryPolicy};
+use so2_tool::app::api_loader::error::LoaderError;
+use so2_tool::app::api_loader::{APILoader, FetchPolicy, Freshness};
+use so2_tool::app::cache::Cacheable;
+use so2_tool::app::cache::compression::Compression;
+use so2_tool::app::cache::meta::CacheMeta;
+use so2_tool::app::cache::store::{CacheStore, FsStore, Stored, StoredInfo};
+
+use common::{Scripted, block_on, fixture, temp_dir};
+
+fn loader(root: &Path, fetch: &Scripted) -> APILoader<Sale> {
+    let origin = "https://so2-api.mutoys.com/".parse().unwrap();
+    let client = ApiClient::new(origin, fetch.clone()).with_retry(RetryPolicy::NONE);
+    let mut loader = APILoader::with_client(Sale, client);
+    loader.set_cache_root(root.to_path_buf());
+    loader
+}
+
+/// 取得済みのキャッシュを置く
+fn fresh_cache(loader: &APILoader<Sale>) {
+    loader.save_cache(&fixture("sale.json")).unwrap();
+}
+
+/// 1日前に取得したキャッシュを置く
+fn stale_cache(loader: &APILoader<Sale>) {
+    fresh_cache(loader);
+    let store = loader.store();
+    let key = Sale.file_path();
+    let mut meta = store.read_meta(&key).unwrap();
+    meta.fetched_at -= chrono::TimeDelta::days(1);
+    store.write_meta(&key, &meta).unwrap();
+}
+
+fn unavailable() -> (StatusCode, Vec<u8>) {
+    (StatusCode::SERVICE_UNAVAILABLE, Vec::new())
+}
+
+#[test]
+fn cache_first_uses_fresh_cache() {
+    let root = temp_dir("fetch_policy_cache_first_fresh");
+    let fetch = Scripted::default();
+    let loader = loader(&root, &fetch);
+    fresh_cache(&loader);
+
+    let loaded = block_on(loader.get_with(FetchPolicy::CacheFirst)).unwrap();
+    assert!(loaded.is_fresh());
+    assert_eq!(loaded.value.len(), 2);
+    assert_eq!(fetch.calls(), 0);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn cache_first_falls_back_to_stale_cache() {
+    let root = temp_dir("fetch_policy_cache_first_stale");
+    let fetch = Scripted::new([unavailable()]);
+    let loader = loader(&root, &fetch);
+    stale_cache(&loader);
+
+    let loaded = block_on(loader.get_with(FetchPolicy::CacheFirst)).unwrap();
+    match loaded.freshness {
+        Freshness::Stale { age, .. } => assert!(age >= Duration::from_secs(24 * 3600)),
+        Freshness::Fresh => panic!("expected stale"),
+    }
+    assert_eq!(loaded.value.len(), 2);
+    assert_eq!(fetch.calls(), 1);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn get_does_not_return_stale_cache() {
+    let root = temp_dir("fetch_policy_get_strict");
+    let fetch = Scripted::new([unavailable(), unavailable()]);
+    let loader = loader(&root, &fetch);
+    stale_cache(&loader);
+
+    match block_on(loader.get()) {
+        Err(LoaderError::HttpStatus { status, .. }) => {
+            assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE)
+        }
+        other => panic!("unexpected: {other:?}"),
+    }
+    match block_on(loader.get_shared()) {
+        Err(LoaderError::HttpStatus { .. }) => {}
+        other => panic!("unexpected: {other:?}"),
+    }
+    assert_eq!(fetch.calls(), 2);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn cache_only_does_not_fetch() {
+    let root = temp_dir("fetch_policy_cache_only");
+    let fetch = Scripted::default();
+    let loader = loader(&root, &fetch);
+
+    match block_on(loader.get_with(FetchPolicy::CacheOnly)) {
+        Err(LoaderError::CacheIo { source, .. }) => {
+            assert_eq!(source.kind(), std::io::ErrorKind::NotFound)
+        }
+        other => panic!("unexpected: {other:?}"),
+    }
+    stale_cache(&loader);
+    let loaded = block_on(loader.get_with(FetchPolicy::CacheOnly)).unwrap();
+    assert!(!loaded.is_fresh());
+    assert_eq!(loaded.value.len(), 2);
+    assert_eq!(fetch.calls(), 0);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn stale_while_revalidate_updates_in_background() {
+    let root = temp_dir("fetch_policy_stale_while_revalidate");
+    let fetch = Scripted::new([(StatusCode::OK, fixture("sale.json"))]);
+    let loader = loader(&root, &fetch);
+    stale_cache(&loader);
+
+    block_on(async {
+        let loaded = loader
+            .get_with(FetchPolicy::StaleWhileRevalidate)
+            .await
+            .unwrap();
+        assert!(!loaded.is_fresh());
+        assert_eq!(loaded.value.len(), 2);
+        // 裏での取得が終わるまで待つ
+        for _ in 0..100 {
+            if loader.load_cache().is_ok() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    });
+    assert_eq!(fetch.calls(), 1);
+    assert!(loader.load_cache().is_ok());
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn network_first_fetches_even_if_fresh() {
+    let root = temp_dir("fetch_policy_network_first");
+    let fetch = Scripted::new([(StatusCode::OK, fixture("sale.json")), unavailable()]);
+    let loader = loader(&root, &fetch);
+    fresh_cache(&loader);
+
+    let loaded = block_on(loader.get_with(FetchPolicy::NetworkFirst)).unwrap();
+    assert!(loaded.is_fresh());
+    assert_eq!(fetch.calls(), 1);
+
+    // 失敗した場合はキャッシュを返す
+    let loaded = block_on(loader.get_with(FetchPolicy::NetworkFirst)).unwrap();
+    assert_eq!(loaded.value.len(), 2);
+    assert_eq!(fetch.calls(), 2);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+/// キャッシュ本体が読めない (メタデータや書き込みは使える) 保存先
+#[derive(Debug)]
+struct Unreadable(FsStore);
+
+impl CacheStore for Unreadable {
+    fn location(&self, key: &Path) -> PathBuf {
+        self.0.location(key)
+    }
+
+    fn read(&self, _key: &Path) -> std::io::Result<Option<Stored>> {
+        Err(std::io::ErrorKind::PermissionDenied.into())
+    }
+
+    fn read_meta(&self, key: &Path) -> Option<CacheMeta> {
+        self.0.read_meta(key)
+    }
+
+    fn write(
+        &self,
+        key: &Path,
+        bytes: &[u8],
+        compression: Compression,
+        meta: &CacheMeta,
+    ) -> std::io::Result<()> {
+        self.0.write(key, bytes, compression, meta)
+    }
+
+    fn write_meta(&self, key: &Path, meta: &CacheMeta) -> std::io::Result<()> {
+        self.0.write_meta(key, meta)
+    }
+
+    fn remove(&self, key: &Path) -> std::io::Result<()> {
+        self.0.remove(key)
+    }
+
+    fn list(&self, dir: &Path) -> std::io::Result<Vec<StoredInfo>> {
+        self.0.list(dir)
+    }
+
+    fn lock_path(&self, key: &Path) -> PathBuf {
+        self.0.lock_path(key)
+    }
+}
+
+fn unreadable(root: &Path, fetch: &Scripted) -> APILoader<Sale> {
+    let mut loader = loader(root, fetch);
+    loader.set_store(Arc::new(Unreadable(FsStore::new(root))));
+    loader
+}
+
+#[test]
+fn unreadable_cache_is_a_miss() {
+    let root = temp_dir("fetch_policy_unreadable_miss");
+    let fetch = Scripted::new([(StatusCode::OK, fixture("sale.json"))]);
+    let loader = unreadable(&root, &fetch);
+
+    let loaded = block_on(loader.get_with(FetchPolicy::CacheFirst)).unwrap();
+    assert!(loaded.is_fresh());
+    assert_eq!(loaded.value.len(), 2);
+    assert_eq!(fetch.calls(), 1);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn unreadable_cache_keeps_network_error() {
+    let root = temp_dir("fetch_policy_unreadable_network");
+    let fetch = Scripted::new([(StatusCode::NOT_FOUND, Vec::new())]);
+    let loader = unreadable(&root, &fetch);
+
+    match block_on(loader.get_with(FetchPolicy::NetworkFirst)) {
+        Err(LoaderError::HttpStatus { status, .. }) => assert_eq!(status, StatusCode::NOT_FOUND),
+        other => panic!("unexpected: {other:?}"),
+    }
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn unreadable_cache_is_reported_by_cache_only() {
+    let root = temp_dir("fetch_policy_unreadable_cache_only");
+    let fetch = Scripted::default();
+    let loader = unreadable(&root, &fetch);
+
+    match block_on(loader.get_with(FetchPolicy::CacheOnly)) {
+        Err(LoaderError::CacheIo { source, .. }) => {
+            assert_eq!(source.kind(), std::io::ErrorKind::PermissionDenied)
+        }
+        other => panic!("unexpected: {other:?}"),
+    }
+    assert_eq!(fetch.calls(), 0);
+    std::fs::remove_dir_all(root).unwrap();
+}
@@ -0,0 +1,70 @@
+//! [LocalDirFetch] でサーバーの代わりにディレクトリから読む
+
+mod common;
+
+use futures::StreamExt;
+use so2_tool::api::schema::{Sale, Shop};
+use so2_tool::app::api_client::{ApiClient, LocalDirFetch, RetryPolicy};
+use so2_tool::app::api_loader::APILoader;
+use so2_tool::app::api_loader::error::LoaderError;
+
+use common::{block_on, files, fixture, temp_dir};
+
+/// `root/server` をサーバー, `root/cache` をキャッシュの保存先にする
+fn loader<S: so2_tool::api::schema::Schema>(root: &std::path::Path, schema: S) -> APILoader<S> {
+    let origin = "https://so2-api.mutoys.com/".parse().unwrap();
+    let fetch = LocalDirFetch {
+        root: root.join("server"),
+    };
+    let client = ApiClient::new(origin, fetch).with_retry(RetryPolicy::NONE);
+    let mut loader = APILoader::with_client(schema, client);
+    loader.set_cache_root(root.join("cache"));
+    loader
+}
+
+fn serve(root: &std::path::Path, path: &str, body: &[u8]) {
+    let path = root.join("server").join(path);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, body).unwrap();
+}
+
+#[test]
+fn get_reads_and_caches() {
+    let root = temp_dir("local_dir_get");
+    serve(&root, "json/sale/all.json", &fixture("sale.json"));
+    let loader = loader(&root, Sale);
+
+    let sales = block_on(loader.get()).unwrap();
+    assert_eq!(sales.len(), 2);
+    assert_eq!(sales[0].sale_serial, 1001);
+
+    // 保存したキャッシュを読める
+    assert!(!files(&root.join("cache")).is_empty());
+    assert_eq!(loader.load_cache().unwrap().len(), 2);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn missing_file_is_not_found() {
+    let root = temp_dir("local_dir_missing");
+    let loader = loader(&root, Shop);
+
+    match block_on(loader.get()) {
+        Err(LoaderError::HttpStatus { status, .. }) => assert_eq!(status.as_u16(), 404),
+        other => panic!("unexpected: {other:?}"),
+    }
+    assert!(files(&root.join("cache")).is_empty());
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn stream_reads_elements() {
+    let root = temp_dir("local_dir_stream");
+    serve(&root, "json/sale/all.json", &fixture("sale.json"));
+    let loader = loader(&root, Sale);
+
+    let sales = block_on(async { loader.stream().await.unwrap().collect::<Vec<_>>().await });
+    let serials = sales.into_iter().map(|sale| sale.unwrap().sale_serial);
+    assert_eq!(serials.collect::<Vec<_>>(), [1001, 1002]);
+    std::fs::remove_dir_all(root).unwrap();
+}
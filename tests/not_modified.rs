@@ -0,0 +1,138 @@
+//! 条件付きリクエストで 304 を受けた場合は, キャッシュ本体を書き換えずにメタデータだけ更新する
+
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use reqwest::StatusCode;
+use so2_tool::api::schema::Sale;
+use so2_tool::app::api_client::error::TransportError;
+use so2_tool::app::api_client::{
+    ApiClient, BoxFuture, Fetch, FetchRequest, FetchResponse, ResponseHeaders, RetryPolicy,
+};
+use so2_tool::app::api_loader::{APILoader, FetchPolicy};
+use so2_tool::app::cache::Cacheable;
+use so2_tool::app::cache::compression::Compression;
+
+use common::{block_on, files, fixture, temp_dir};
+
+const ETAG: &str = "\"sale-1\"";
+
+/// `ETag` を付けて応答し, `If-None-Match` が一致すれば 304 を返す
+#[derive(Clone, Default)]
+struct ETagServer {
+    /// 受けたリクエストの `If-None-Match`
+    requests: Arc<Mutex<Vec<Option<String>>>>,
+}
+
+impl ETagServer {
+    fn requests(&self) -> Vec<Option<String>> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Fetch for ETagServer {
+    fn fetch<'a>(
+        &'a self,
+        request: &'a FetchRequest,
+    ) -> BoxFuture<'a, Result<FetchResponse, TransportError>> {
+        let if_none_match = request.validators.etag.clone();
+        let mut requests = self.requests.lock().unwrap();
+        requests.push(if_none_match.clone());
+        let headers = ResponseHeaders {
+            date: Some(format!("request {}", requests.len())),
+            last_modified: None,
+            etag: Some(ETAG.to_string()),
+        };
+        let (status, body) = match if_none_match.as_deref() {
+            Some(ETAG) => (StatusCode::NOT_MODIFIED, Vec::new()),
+            _ => (StatusCode::OK, fixture("sale.json")),
+        };
+        Box::pin(async move {
+            Ok(FetchResponse {
+                status,
+                headers,
+                body,
+            })
+        })
+    }
+}
+
+fn loader(root: &Path, server: &ETagServer) -> APILoader<Sale> {
+    let origin = "https://so2-api.mutoys.com/".parse().unwrap();
+    let client = ApiClient::new(origin, server.clone()).with_retry(RetryPolicy::NONE);
+    let mut loader = APILoader::with_client(Sale, client);
+    loader.set_cache_root(root.to_path_buf());
+    loader
+}
+
+/// キャッシュ本体のファイル
+fn cache_file(root: &Path) -> PathBuf {
+    files(root)
+        .into_iter()
+        .find(|path| path.extension().is_some_and(|ext| ext == "gz"))
+        .expect("gzip cache")
+}
+
+/// 取得時刻を1日前にして期限切れにする
+fn expire(loader: &APILoader<Sale>) {
+    let store = loader.store();
+    let mut meta = store.read_meta(&Sale.file_path()).unwrap();
+    meta.fetched_at -= chrono::TimeDelta::days(1);
+    store.write_meta(&Sale.file_path(), &meta).unwrap();
+}
+
+#[test]
+fn not_modified_refreshes_meta() {
+    let root = temp_dir("not_modified_meta");
+    let server = ETagServer::default();
+    let loader = loader(&root, &server);
+    block_on(loader.get()).unwrap();
+    expire(&loader);
+    assert!(loader.load_cache().is_err());
+
+    let file = cache_file(&root);
+    let bytes = std::fs::read(&file).unwrap();
+    let modified = std::fs::metadata(&file).unwrap().modified().unwrap();
+
+    let loaded = block_on(loader.get_with(FetchPolicy::CacheFirst)).unwrap();
+    assert!(loaded.is_fresh());
+    assert_eq!(loaded.value.len(), 2);
+    assert_eq!(server.requests(), [None, Some(ETAG.to_string())]);
+
+    // メタデータは更新され, 本体は書き換えない
+    let meta = loader.store().read_meta(&Sale.file_path()).unwrap();
+    assert_eq!(meta.server_date.as_deref(), Some("request 2"));
+    assert_eq!(meta.etag.as_deref(), Some(ETAG));
+    assert!(meta.fetched_at().elapsed().unwrap().as_secs() < 60);
+    assert_eq!(std::fs::read(&file).unwrap(), bytes);
+    assert_eq!(
+        std::fs::metadata(&file).unwrap().modified().unwrap(),
+        modified
+    );
+    assert_eq!(loader.load_cache().unwrap().len(), 2);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn unusable_cache_is_refetched() {
+    let root = temp_dir("not_modified_refetch");
+    let server = ETagServer::default();
+    let loader = loader(&root, &server);
+    block_on(loader.get()).unwrap();
+
+    // メタデータ (ETag) は残っているが本体が読めない
+    let broken = Compression::Gzip.compress(b"<html>").unwrap();
+    std::fs::write(cache_file(&root), broken).unwrap();
+
+    let loaded = block_on(loader.get_with(FetchPolicy::NetworkFirst)).unwrap();
+    assert!(loaded.is_fresh());
+    assert_eq!(loaded.value.len(), 2);
+    // 304 を受けた後, 条件無しで取り直す
+    assert_eq!(server.requests(), [None, Some(ETAG.to_string()), None]);
+    let meta = loader.store().read_meta(&Sale.file_path()).unwrap();
+    assert_eq!(meta.server_date.as_deref(), Some("request 3"));
+    assert_eq!(loader.load_cache().unwrap().len(), 2);
+    std::fs::remove_dir_all(root).unwrap();
+}
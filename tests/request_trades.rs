@@ -0,0 +1,37 @@
+//! 時間帯毎の注文レポートをまとめる
+
+mod common;
+
+use chrono::Days;
+use so2_tool::api::schema::RequestReport;
+use so2_tool::app::api_client::{ApiClient, LocalDirFetch};
+use so2_tool::app::api_loader::APILoader;
+use so2_tool::app::api_loader::error::LoaderError;
+use so2_tool::app::request_trades::{DEFAULT_CONCURRENCY, load_day};
+
+use common::{block_on, files, temp_dir};
+
+#[test]
+fn unpublished_hours_are_missing() {
+    let root = temp_dir("request_trades_unpublished");
+    let origin = "https://so2-api.mutoys.com/".parse().unwrap();
+    let fetch = LocalDirFetch {
+        root: root.join("server"),
+    };
+    // どのタイムゾーンでもまだ公開されていない日
+    let date = chrono::Local::now().date_naive() + Days::new(2);
+    let schema = RequestReport::All { date, hour: 0 };
+    let mut loader = APILoader::with_client(schema, ApiClient::new(origin, fetch));
+    loader.set_cache_root(root.join("cache"));
+
+    let trades = block_on(load_day(&loader, date, DEFAULT_CONCURRENCY));
+
+    assert!(!trades.is_complete());
+    assert_eq!(trades.missing.len(), 24);
+    assert!(
+        trades.missing.iter().all(|(missing, _, e)| *missing == date
+            && matches!(e, LoaderError::InvalidParameters { .. }))
+    );
+    assert!(files(&root.join("cache")).is_empty());
+    std::fs::remove_dir_all(root).unwrap();
+}
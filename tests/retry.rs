@@ -0,0 +1,95 @@
+//! 一時的な失敗だけを再試行し, 読めない応答はキャッシュしない
+
+mod common;
+
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use so2_tool::api::schema::Sale;
+use so2_tool::app::api_client::{ApiClient, RetryPolicy};
+use so2_tool::app::api_loader::APILoader;
+use so2_tool::app::api_loader::error::LoaderError;
+
+use common::{Scripted, block_on, files, fixture, temp_dir};
+
+const RETRY: RetryPolicy = RetryPolicy {
+    max_retries: 3,
+    base_delay: Duration::from_millis(1),
+    max_delay: Duration::from_millis(1),
+};
+
+fn loader(name: &str, fetch: &Scripted) -> APILoader<Sale> {
+    let origin = "https://so2-api.mutoys.com/".parse().unwrap();
+    let client = ApiClient::new(origin, fetch.clone()).with_retry(RETRY);
+    let mut loader = APILoader::with_client(Sale, client);
+    loader.set_cache_root(temp_dir(name));
+    loader
+}
+
+#[test]
+fn server_error_is_retried() {
+    let fetch = Scripted::new([
+        (StatusCode::SERVICE_UNAVAILABLE, Vec::new()),
+        (StatusCode::OK, fixture("sale.json")),
+    ]);
+    let loader = loader("retry_server_error", &fetch);
+
+    let sales = block_on(loader.get()).unwrap();
+    assert_eq!(sales.len(), 2);
+    assert_eq!(fetch.calls(), 2);
+    assert!(loader.load_cache().is_ok());
+    std::fs::remove_dir_all(&loader.cache_root).unwrap();
+}
+
+#[test]
+fn retries_are_limited() {
+    let fetch = Scripted::default();
+    let loader = loader("retry_limited", &fetch);
+
+    match block_on(loader.get()) {
+        Err(LoaderError::HttpStatus { status, .. }) => {
+            assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR)
+        }
+        other => panic!("unexpected: {other:?}"),
+    }
+    assert_eq!(fetch.calls(), 1 + RETRY.max_retries as usize);
+    assert!(files(&loader.cache_root).is_empty());
+    std::fs::remove_dir_all(&loader.cache_root).unwrap();
+}
+
+#[test]
+fn client_error_is_not_retried() {
+    let fetch = Scripted::new([(StatusCode::NOT_FOUND, Vec::new())]);
+    let loader = loader("retry_client_error", &fetch);
+
+    match block_on(loader.get()) {
+        Err(LoaderError::HttpStatus { status, .. }) => assert_eq!(status, StatusCode::NOT_FOUND),
+        other => panic!("unexpected: {other:?}"),
+    }
+    assert_eq!(fetch.calls(), 1);
+    assert!(files(&loader.cache_root).is_empty());
+    std::fs::remove_dir_all(&loader.cache_root).unwrap();
+}
+
+#[test]
+fn undecodable_body_is_not_cached() {
+    let html = b"<!DOCTYPE html><html><body>maintenance</body></html>".to_vec();
+    let fetch = Scripted::new([(StatusCode::OK, html)]);
+    let loader = loader("retry_undecodable", &fetch);
+
+    match block_on(loader.get()) {
+        Err(LoaderError::Decode { .. }) => {}
+        other => panic!("unexpected: {other:?}"),
+    }
+    assert_eq!(fetch.calls(), 1);
+    // 調査用に隔離はするが, キャッシュとしては保存しない
+    let root = &loader.cache_root;
+    let quarantine = root.join("quarantine");
+    assert!(files(root).iter().all(|path| path.starts_with(&quarantine)));
+    let corrupt = files(&quarantine)
+        .into_iter()
+        .filter(|path| path.extension().is_some_and(|ext| ext == "corrupt"));
+    assert_eq!(corrupt.count(), 1);
+    assert!(loader.load_cache().is_err());
+    std::fs::remove_dir_all(&loader.cache_root).unwrap();
+}
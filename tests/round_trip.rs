@@ -0,0 +1,61 @@
+//! サーバーの応答 (`tests/fixtures`) を読んで書き出すと元に戻る
+
+mod common;
+
+use serde_json::Value;
+use so2_tool::api::schema::*;
+
+use common::fixture;
+
+fn round_trip<S: Schema>(name: &str) {
+    let bytes = fixture(name);
+    let response: S::Response = (S::format().decode(&bytes))
+        .unwrap_or_else(|(json_path, e)| panic!("{name}: {e} at {json_path}"));
+    let encoded = S::format().encode(&response).unwrap();
+
+    match S::format() {
+        // キーの順番と空白は問わない
+        Format::Json => {
+            let expected: Value = serde_json::from_slice(&bytes).unwrap();
+            let actual: Value = serde_json::from_slice(&encoded).unwrap();
+            assert_eq!(actual, expected, "{name}");
+        }
+        // 末尾のカンマと改行は付けない
+        Format::Fragments => {
+            let expected = bytes.trim_ascii_end().strip_suffix(b",").unwrap();
+            assert_eq!(
+                String::from_utf8_lossy(&encoded),
+                String::from_utf8_lossy(expected),
+                "{name}"
+            );
+        }
+    }
+}
+
+macro_rules! round_trip {
+    ($($test:ident: $schema:ty => $fixture:literal,)+) => {
+        $(
+            #[test]
+            fn $test() {
+                round_trip::<$schema>($fixture);
+            }
+        )+
+    };
+}
+
+round_trip! {
+    official_item: OfficialItem => "item.json",
+    recipe_item: RecipeItem => "recipe_item.json",
+    area: Area => "area.json",
+    report: Report => "report.json",
+    ranking_all_monthly: RankingAllMonthly => "ranking_all_monthly.json",
+    ranking_section_monthly: RankingSectionMonthly => "ranking_section_monthly.json",
+    ranking_section_daily: RankingSectionDaily => "ranking_daily.json",
+    sale: Sale => "sale.json",
+    request: Request => "request.json",
+    shop_summary: ShopSummary => "shop_summary.json",
+    shop: Shop => "shop.json",
+    people: People => "people.json",
+    request_report: RequestReport => "request_report.json",
+    area_summary: AreaSummary => "area_summary.json",
+}